@@ -0,0 +1,31 @@
+use airsim::{airsim::Client, car::Car, errors::NetworkResult};
+use async_std::task;
+use futures::StreamExt;
+use std::time::Duration;
+
+async fn run_car() -> NetworkResult<()> {
+    let address = "127.0.0.1:41451";
+    let client = Client::connect(address).await?;
+    client.reset().await?;
+
+    // a second connection keeps polling while the first one drives
+    let watcher = Client::connect(address).await?;
+    let mut car = Car::new(client);
+    car.go_forward().await?;
+
+    let mut collisions = Box::pin(watcher.collisions("", Duration::from_millis(50)));
+    if let Some(collision) = collisions.next().await {
+        let collision = collision?;
+        println!(
+            "hit {} at {:?}",
+            collision.object_name, collision.impact_point
+        );
+    }
+    car.stop().await?;
+    task::sleep(Duration::from_secs(1)).await;
+    Ok(())
+}
+
+fn main() -> NetworkResult<()> {
+    task::block_on(run_car())
+}
@@ -96,16 +96,8 @@ async fn run_car() -> NetworkResult<()> {
         }
         if let Some(gamepad) = active_gamepad.map(|id| gilrs.gamepad(id)) {
             // Throttle management
-            if gamepad.is_pressed(Button::South) {
-                gamepad_controller.gamepad.south = true;
-            } else {
-                gamepad_controller.gamepad.south = false;
-            }
-            if gamepad.is_pressed(Button::West) {
-                gamepad_controller.gamepad.west = true;
-            } else {
-                gamepad_controller.gamepad.west = false;
-            }
+            gamepad_controller.gamepad.south = gamepad.is_pressed(Button::South);
+            gamepad_controller.gamepad.west = gamepad.is_pressed(Button::West);
 
             if let Some(right_trigger) = gamepad.button_code(Button::RightTrigger2) {
                 gamepad_controller.gamepad.analog_throttle = gamepad.state().value(right_trigger);
@@ -119,24 +111,12 @@ async fn run_car() -> NetworkResult<()> {
             if let Some(data) = gamepad.axis_data(Axis::LeftStickX) {
                 gamepad_controller.gamepad.analog_steer = data.value();
             }
-            if gamepad.is_pressed(Button::DPadLeft) {
-                gamepad_controller.gamepad.dpad_left = true;
-            } else {
-                gamepad_controller.gamepad.dpad_left = false;
-            }
-            if gamepad.is_pressed(Button::DPadRight) {
-                gamepad_controller.gamepad.dpad_right = true;
-            } else {
-                gamepad_controller.gamepad.dpad_right = false;
-            }
-            if gamepad.is_pressed(Button::Start) {
-                gamepad_controller.gamepad.start = true;
-            } else {
-                gamepad_controller.gamepad.start = false;
-            }
+            gamepad_controller.gamepad.dpad_left = gamepad.is_pressed(Button::DPadLeft);
+            gamepad_controller.gamepad.dpad_right = gamepad.is_pressed(Button::DPadRight);
+            gamepad_controller.gamepad.start = gamepad.is_pressed(Button::Start);
         }
 
-        if gamepad_controller.gamepad.start == true {
+        if gamepad_controller.gamepad.start {
             return Ok(());
         }
 
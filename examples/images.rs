@@ -0,0 +1,32 @@
+use airsim::airsim::Client;
+use airsim::camera::{ImageData, ImageRequest, ImageType};
+use airsim::errors::NetworkResult;
+use async_std::{fs, task};
+
+async fn capture() -> NetworkResult<()> {
+    let address = "127.0.0.1:41451";
+    let client = Client::connect(address).await?;
+
+    let mut depth = ImageRequest::new("0", ImageType::DepthPlanar);
+    depth.pixels_as_float = true;
+    depth.compress = false;
+    let requests = [ImageRequest::new("0", ImageType::Scene), depth];
+
+    for response in client.sim_get_images(&requests, "").await? {
+        match response.data {
+            ImageData::Uint8(png) => {
+                fs::write("scene.png", png).await?;
+                println!("saved a {}x{} scene", response.width, response.height);
+            }
+            ImageData::Float(depth) => {
+                let closest = depth.iter().cloned().fold(f32::INFINITY, f32::min);
+                println!("closest obstacle is {} meters away", closest);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> NetworkResult<()> {
+    task::block_on(capture())
+}
@@ -1,10 +1,8 @@
-use airsim::airsim::Client;
-use airsim::controller::Car;
 use airsim::errors::NetworkResult;
 use async_std::task;
 
 #[cfg(feature = "keyboard")]
-use airsim::controller::keyboard::Controller;
+use airsim::{airsim::Client, controller::keyboard::Controller, controller::Car};
 
 #[cfg(feature = "keyboard")]
 async fn run_car() -> NetworkResult<()> {
@@ -0,0 +1,48 @@
+use airsim::errors::NetworkResult;
+use airsim::multirotor::{DrivetrainType, MultirotorClient};
+use airsim::types::Vector3r;
+use async_std::task;
+use std::time::Duration;
+
+async fn fly() -> NetworkResult<()> {
+    let address = "127.0.0.1:41451";
+    let drone = MultirotorClient::connect(address).await?;
+    drone.reset().await?;
+    drone.arm_disarm(true, "").await?;
+
+    drone.takeoff(20., "").await?;
+    drone.hover("").await?;
+    task::sleep(Duration::from_secs(3)).await;
+    let state = drone.get_multirotor_state("").await?;
+    println!(
+        "hovering at {:?}, landed state is {:?}",
+        state.kinematics_estimated.position, state.landed_state
+    );
+
+    drone
+        .move_on_path(
+            vec![
+                Vector3r::new(20., 0., -10.),
+                Vector3r::new(20., 20., -10.),
+                Vector3r::new(0., 20., -10.),
+            ],
+            5.,
+        )
+        .drivetrain(DrivetrainType::ForwardOnly)
+        .timeout_sec(60.)
+        .send()
+        .await?;
+    drone
+        .move_to_position(Vector3r::new(0., 0., -5.), 5.)
+        .send()
+        .await?;
+
+    drone.land(60., "").await?;
+    drone.arm_disarm(false, "").await?;
+    println!("Touchdown!");
+    Ok(())
+}
+
+fn main() -> NetworkResult<()> {
+    task::block_on(fly())
+}
@@ -0,0 +1,29 @@
+use airsim::{
+    airsim::{CarControls, Client},
+    car::Stepper,
+    errors::NetworkResult,
+};
+use async_std::task;
+
+async fn run_car() -> NetworkResult<()> {
+    let address = "127.0.0.1:41451";
+    let client = Client::connect(address).await?;
+    client.reset().await?;
+
+    let stepper = Stepper::new(&client, 5);
+    let mut controls = CarControls {
+        throttle: 0.5,
+        ..CarControls::default()
+    };
+    for step in 0..100 {
+        let state = stepper.step(&controls).await?;
+        // a tiny proportional controller holding 5 m/s, updated every 5 frames
+        controls.throttle = (f64::from(5. - state.speed) * 0.2).clamp(0., 1.);
+        println!("step {}: {:.2} m/s", step, state.speed);
+    }
+    stepper.resume().await
+}
+
+fn main() -> NetworkResult<()> {
+    task::block_on(run_car())
+}
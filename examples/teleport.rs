@@ -0,0 +1,38 @@
+use airsim::{
+    airsim::Client,
+    car::Car,
+    errors::NetworkResult,
+    types::{Pose, Quaternionr, Vector3r},
+};
+use async_std::task;
+use std::time::Duration;
+
+async fn run_car() -> NetworkResult<()> {
+    let address = "127.0.0.1:41451";
+    let client = Client::connect(address).await?;
+    client.reset().await?;
+
+    let start = client.sim_get_vehicle_pose("").await?;
+    println!("starting at {:?}", start.position);
+
+    // jump 20 meters ahead, facing east
+    let destination = Pose::new(
+        start.position + Vector3r::new(20., 0., 0.),
+        Quaternionr::from_euler_angles(0., 0., std::f32::consts::FRAC_PI_2),
+    );
+    client.sim_set_vehicle_pose(destination, true, "").await?;
+    println!(
+        "now at {:?}",
+        client.sim_get_vehicle_pose("").await?.position
+    );
+
+    let mut car = Car::new(client);
+    car.go_forward().await?;
+    task::sleep(Duration::from_secs(3)).await;
+    car.stop().await?;
+    Ok(())
+}
+
+fn main() -> NetworkResult<()> {
+    task::block_on(run_car())
+}
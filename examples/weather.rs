@@ -0,0 +1,33 @@
+use airsim::{airsim::Client, errors::NetworkResult, types::Vector3r, weather::WeatherParameter};
+use async_std::task;
+use std::time::Duration;
+
+async fn run() -> NetworkResult<()> {
+    let address = "127.0.0.1:41451";
+    let client = Client::connect(address).await?;
+
+    client
+        .sim_set_time_of_day(true)
+        .start_datetime("2020-06-21 20:30:00")
+        .celestial_clock_speed(60.)
+        .update_interval_secs(1.)
+        .send()
+        .await?;
+    client.sim_set_wind(Vector3r::new(0., 5., 0.)).await?;
+
+    client.sim_enable_weather(true).await?;
+    for parameter in [WeatherParameter::Rain, WeatherParameter::Fog] {
+        for step in 0..=4 {
+            let value = step as f32 / 4.;
+            println!("{:?} at {}", parameter, value);
+            client.sim_set_weather_parameter(parameter, value).await?;
+            task::sleep(Duration::from_secs(2)).await;
+        }
+        client.sim_set_weather_parameter(parameter, 0.).await?;
+    }
+    client.sim_enable_weather(false).await
+}
+
+fn main() -> NetworkResult<()> {
+    task::block_on(run())
+}
@@ -1,48 +1,101 @@
 use crate::{
+    codec,
     errors::{NetworkError, NetworkResult},
     msgpack::Client as MsgPackClient,
+    types::{EnvironmentState, KinematicsState, Pose},
 };
-use async_std::{channel::RecvError, net::ToSocketAddrs};
+use async_std::net::ToSocketAddrs;
 use rmp_rpc::message::{Notification, Request, Response};
 use rmpv::Value;
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
+use std::collections::HashSet;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::Duration;
+
+mod heartbeat;
+mod reconnect;
+
+pub use heartbeat::ConnectionHealth;
+use heartbeat::HealthStats;
+pub use reconnect::{Backoff, ConnectionState};
 
 pub struct Client {
-    client: MsgPackClient,
-    last_request_id: AtomicU32,
+    /// Replaced by a new connection when reconnecting
+    connection: Arc<RwLock<Arc<MsgPackClient>>>,
+    /// Shared with the heartbeat, which sends pings on the same connection
+    last_request_id: Arc<AtomicU32>,
+    timeout: Option<Duration>,
+    /// Vehicles we enabled API control for, to enable it again after reconnecting
+    api_control: Arc<Mutex<HashSet<String>>>,
+    health: Arc<Mutex<HealthStats>>,
 }
 
+/// How long the simulator gets to answer the requests made by `Client::connect`
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 impl Client {
+    /// Connects, then pings the simulator and enables API control for the default vehicle.
+    ///
+    /// A simulator that accepts the connection but does not answer within 10 seconds makes this
+    /// fail with `NetworkError::Timeout`.
     pub async fn connect(addrs: impl ToSocketAddrs) -> NetworkResult<Self> {
-        let car = Self {
-            last_request_id: AtomicU32::new(0),
-            client: MsgPackClient::connect(addrs).await?,
+        let mut client = Self {
+            connection: Arc::new(RwLock::new(Arc::new(MsgPackClient::connect(addrs).await?))),
+            last_request_id: Arc::default(),
+            timeout: Some(CONNECT_TIMEOUT),
+            api_control: Arc::default(),
+            health: Arc::default(),
         };
-        car.ping().await?;
-        car.enable_api_control().await?;
-        Ok(car)
+        client.ping().await?;
+        client.enable_api_control(true, "").await?;
+        client.timeout = None;
+        Ok(client)
     }
 
     pub async fn ping(&self) -> NetworkResult<Response> {
-        self.client
-            .request(Request {
-                id: self.new_request_id(),
-                method: "ping".to_string(),
-                params: Vec::new(),
-            })
+        self.connection()
+            .request_with_timeout(
+                Request {
+                    id: self.new_request_id(),
+                    method: "ping".to_string(),
+                    params: Vec::new(),
+                },
+                self.timeout,
+            )
             .await
-            .map_err(Into::into)
+    }
+
+    pub async fn enable_api_control(
+        &self,
+        is_enabled: bool,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        // the simulator answers with nil, the mock server with a bool
+        self.call::<_, IgnoredAny>("enableApiControl", (is_enabled, vehicle_name))
+            .await?;
+        let mut vehicles = self
+            .api_control
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if is_enabled {
+            vehicles.insert(vehicle_name.to_string());
+        } else {
+            vehicles.remove(vehicle_name);
+        }
+        Ok(())
     }
 
     pub async fn reset(&self) -> NetworkResult<()> {
-        self.client
+        self.connection()
             .notify(Notification {
                 method: "reset".to_string(),
                 params: Vec::new(),
             })
             .await
-            .map_err::<NetworkError, _>(Into::into)?;
-        Ok(())
     }
 
     pub async fn get_client_version(&self) -> NetworkResult<String> {
@@ -50,12 +103,15 @@ impl Client {
     }
 
     pub async fn get_server_version(&self) -> NetworkResult<i64> {
-        self.client
-            .request(Request {
-                id: self.new_request_id(),
-                method: "getServerVersion".to_string(),
-                params: Vec::new(),
-            })
+        self.connection()
+            .request_with_timeout(
+                Request {
+                    id: self.new_request_id(),
+                    method: "getServerVersion".to_string(),
+                    params: Vec::new(),
+                },
+                self.timeout,
+            )
             .await
             .map(|res| {
                 res.result
@@ -63,54 +119,176 @@ impl Client {
                     .as_i64()
                     .unwrap_or(0)
             })
-            .map_err(Into::into)
     }
 
     pub async fn send_car_controls(&self, controls: &CarControls) -> NetworkResult<()> {
-        self.client
-            .request(Request {
-                id: self.new_request_id(),
-                method: "setCarControls".to_string(),
-                params: controls.serialize(),
-            })
-            .await?;
-        Ok(())
+        self.call("setCarControls", (controls, "")).await
     }
 
-    async fn enable_api_control(&self) -> Result<bool, RecvError> {
-        self.client
-            .request(Request {
-                id: self.new_request_id(),
-                method: "enableApiControl".to_string(),
-                params: vec![
-                    rmp_rpc::Value::Boolean(true),
-                    rmp_rpc::Value::String("".into()),
-                ],
-            })
-            .await?;
-        self.client
-            .request(Request {
-                id: self.new_request_id(),
-                method: "enableApiControl".to_string(),
-                params: vec![
-                    rmp_rpc::Value::Boolean(true),
-                    rmp_rpc::Value::String("".into()),
-                ],
-            })
+    pub async fn get_car_state(&self, vehicle_name: &str) -> NetworkResult<CarState> {
+        self.call("getCarState", (vehicle_name,)).await
+    }
+
+    pub async fn sim_get_vehicle_pose(&self, vehicle_name: &str) -> NetworkResult<Pose> {
+        self.call("simGetVehiclePose", (vehicle_name,)).await
+    }
+
+    /// Teleports the vehicle to `pose`, in world NED coordinates.
+    ///
+    /// Unless `ignore_collision` is set, the vehicle is not moved if it would collide with
+    /// something at its destination.
+    pub async fn sim_set_vehicle_pose(
+        &self,
+        pose: Pose,
+        ignore_collision: bool,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call("simSetVehiclePose", (pose, ignore_collision, vehicle_name))
             .await
-            .map_err(Into::into)
-            .map(|response| {
-                response.result.is_ok() && response.result.unwrap().as_bool() == Some(true)
-            })
+    }
+
+    /// The vehicle's actual kinematics, as opposed to the estimate reported in its state.
+    pub async fn sim_get_ground_truth_kinematics(
+        &self,
+        vehicle_name: &str,
+    ) -> NetworkResult<KinematicsState> {
+        self.call("simGetGroundTruthKinematics", (vehicle_name,))
+            .await
+    }
+
+    /// Overrides the vehicle's kinematics, see `sim_set_vehicle_pose` for `ignore_collision`.
+    pub async fn sim_set_kinematics(
+        &self,
+        state: &KinematicsState,
+        ignore_collision: bool,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call("simSetKinematics", (state, ignore_collision, vehicle_name))
+            .await
+    }
+
+    /// The environment around the vehicle: gravity, air pressure and density, temperature, and
+    /// its geographic position.
+    pub async fn sim_get_ground_truth_environment(
+        &self,
+        vehicle_name: &str,
+    ) -> NetworkResult<EnvironmentState> {
+        self.call("simGetGroundTruthEnvironment", (vehicle_name,))
+            .await
+    }
+
+    pub async fn sim_pause(&self, is_paused: bool) -> NetworkResult<()> {
+        self.call("simPause", (is_paused,)).await
+    }
+
+    pub async fn sim_is_paused(&self) -> NetworkResult<bool> {
+        self.call("simIsPaused", ()).await
+    }
+
+    /// Resumes the simulation, then pauses it again after `seconds` of simulated time.
+    ///
+    /// This returns right away, poll `sim_is_paused` to know when the simulation stopped.
+    pub async fn sim_continue_for_time(&self, seconds: f64) -> NetworkResult<()> {
+        self.call("simContinueForTime", (seconds,)).await
+    }
+
+    /// Same as `sim_continue_for_time`, for a number of rendered frames.
+    pub async fn sim_continue_for_frames(&self, frames: u32) -> NetworkResult<()> {
+        self.call("simContinueForFrames", (frames,)).await
+    }
+
+    /// Calls `method` on the simulator.
+    ///
+    /// `params` is usually a tuple holding the method arguments in order, and the result is
+    /// deserialized into `R`.
+    pub async fn call<P, R>(&self, method: &str, params: P) -> NetworkResult<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        self.send_call(method, params, self.timeout).await
+    }
+
+    /// Same as `call`, failing with `NetworkError::Timeout` after `timeout` instead of the
+    /// client's default.
+    pub async fn call_with_timeout<P, R>(
+        &self,
+        method: &str,
+        params: P,
+        timeout: Duration,
+    ) -> NetworkResult<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        self.send_call(method, params, Some(timeout)).await
+    }
+
+    /// How long calls wait for the simulator to answer, `None` (the default) waits forever.
+    ///
+    /// Keep in mind that some calls, such as the multirotor moves, only return once the
+    /// command completed.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    async fn send_call<P, R>(
+        &self,
+        method: &str,
+        params: P,
+        timeout: Option<Duration>,
+    ) -> NetworkResult<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let params = match codec::to_value(&params)? {
+            Value::Array(params) => params,
+            Value::Nil => Vec::new(),
+            param => vec![param],
+        };
+        codec::from_value(self.request_with_timeout(method, params, timeout).await?)
+    }
+
+    async fn request_with_timeout(
+        &self,
+        method: &str,
+        params: Vec<Value>,
+        timeout: Option<Duration>,
+    ) -> NetworkResult<Value> {
+        self.connection()
+            .request_with_timeout(
+                Request {
+                    id: self.new_request_id(),
+                    method: method.to_string(),
+                    params,
+                },
+                timeout,
+            )
+            .await?
+            .result
+            .map_err(NetworkError::Rpc)
+    }
+
+    fn connection(&self) -> Arc<MsgPackClient> {
+        current(&self.connection)
     }
 
     fn new_request_id(&self) -> u32 {
-        self.last_request_id
-            .compare_and_swap(u32::max_value(), 0, Ordering::AcqRel);
-        self.last_request_id.fetch_add(1, Ordering::AcqRel)
+        next_request_id(&self.last_request_id)
     }
 }
-#[derive(Default, Debug, Clone)]
+
+fn current(connection: &RwLock<Arc<MsgPackClient>>) -> Arc<MsgPackClient> {
+    Arc::clone(&connection.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+fn next_request_id(last_request_id: &AtomicU32) -> u32 {
+    let _ = last_request_id.compare_exchange(u32::MAX, 0, Ordering::AcqRel, Ordering::Acquire);
+    last_request_id.fetch_add(1, Ordering::AcqRel)
+}
+
+#[derive(Default, Debug, Clone, Serialize)]
 pub struct CarControls {
     pub throttle: f64,
     pub steering: f64,
@@ -121,33 +299,14 @@ pub struct CarControls {
     pub gear_immediate: bool,
 }
 
-impl CarControls {
-    #[must_use]
-    pub fn serialize(&self) -> Vec<Value> {
-        vec![
-            Value::Map(vec![
-                (Value::String("throttle".into()), Value::F64(self.throttle)),
-                (Value::String("steering".into()), Value::F64(self.steering)),
-                (Value::String("brake".into()), Value::F64(self.brake)),
-                (
-                    Value::String("handbrake".into()),
-                    Value::Boolean(self.handbrake),
-                ),
-                (
-                    Value::String("is_manual_gear".into()),
-                    Value::Boolean(self.is_manual_gear),
-                ),
-                (
-                    Value::String("manual_gear".into()),
-                    Value::Integer(self.manual_gear.into()),
-                ),
-                (
-                    Value::String("gear_immediate".into()),
-                    Value::Boolean(self.gear_immediate),
-                ),
-            ]),
-            // TODO: FIGURE OUT WHY ?!
-            Value::String("".into()),
-        ]
-    }
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct CarState {
+    pub speed: f32,
+    pub gear: i32,
+    pub rpm: f32,
+    pub maxrpm: f32,
+    pub handbrake: bool,
+    pub kinematics_estimated: KinematicsState,
+    /// Simulation time in nanoseconds
+    pub timestamp: u64,
 }
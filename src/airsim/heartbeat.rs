@@ -0,0 +1,197 @@
+use super::{current, next_request_id, Client};
+use crate::msgpack::Client as MsgPackClient;
+use async_std::task;
+use rmp_rpc::message::Request;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU32;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+/// Number of round trips the statistics are computed over
+const WINDOW: usize = 100;
+
+/// Shorter heartbeat periods are raised to this, a ping can't be answered in no time
+const MIN_PERIOD: Duration = Duration::from_millis(10);
+
+/// Round trip statistics over the last heartbeats, all empty until `start_heartbeat` is called.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionHealth {
+    pub last_rtt: Option<Duration>,
+    pub mean_rtt: Option<Duration>,
+    pub median_rtt: Option<Duration>,
+    pub p95_rtt: Option<Duration>,
+    pub p99_rtt: Option<Duration>,
+    /// Pings that failed or went unanswered for a whole period since the last answered one
+    pub consecutive_failures: u32,
+    pub last_success: Option<Instant>,
+}
+
+#[derive(Default)]
+pub(super) struct HealthStats {
+    running: bool,
+    rtts: VecDeque<Duration>,
+    consecutive_failures: u32,
+    last_success: Option<Instant>,
+}
+
+impl HealthStats {
+    fn record(&mut self, rtt: Option<Duration>) {
+        match rtt {
+            Some(rtt) => {
+                if self.rtts.len() == WINDOW {
+                    self.rtts.pop_front();
+                }
+                self.rtts.push_back(rtt);
+                self.consecutive_failures = 0;
+                self.last_success = Some(Instant::now());
+            }
+            None => self.consecutive_failures += 1,
+        }
+    }
+
+    fn snapshot(&self) -> ConnectionHealth {
+        let mut sorted: Vec<Duration> = self.rtts.iter().copied().collect();
+        sorted.sort_unstable();
+        // nearest rank percentile
+        let percentile = |p: usize| match sorted.len() {
+            0 => None,
+            len => Some(sorted[(len * p).div_ceil(100) - 1]),
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let mean_rtt = match self.rtts.len() {
+            0 => None,
+            len => Some(self.rtts.iter().sum::<Duration>() / len as u32),
+        };
+        ConnectionHealth {
+            last_rtt: self.rtts.back().copied(),
+            mean_rtt,
+            median_rtt: percentile(50),
+            p95_rtt: percentile(95),
+            p99_rtt: percentile(99),
+            consecutive_failures: self.consecutive_failures,
+            last_success: self.last_success,
+        }
+    }
+}
+
+fn lock(health: &Mutex<HealthStats>) -> MutexGuard<'_, HealthStats> {
+    health.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Client {
+    /// Pings the simulator every `period` in the background, until the client is dropped.
+    ///
+    /// A ping not answered within `period` counts as a failure, so a frozen simulator shows up
+    /// as a growing `consecutive_failures`. Periods under 10ms are raised to 10ms. Calling
+    /// this again while it runs does nothing.
+    pub fn start_heartbeat(&self, period: Duration) {
+        {
+            let mut health = lock(&self.health);
+            if health.running {
+                return;
+            }
+            health.running = true;
+        }
+        task::spawn(heartbeat(
+            period.max(MIN_PERIOD),
+            Arc::downgrade(&self.connection),
+            Arc::downgrade(&self.last_request_id),
+            Arc::downgrade(&self.health),
+        ));
+    }
+
+    #[must_use]
+    pub fn connection_health(&self) -> ConnectionHealth {
+        lock(&self.health).snapshot()
+    }
+}
+
+async fn heartbeat(
+    period: Duration,
+    connection: Weak<RwLock<Arc<MsgPackClient>>>,
+    last_request_id: Weak<AtomicU32>,
+    health: Weak<Mutex<HealthStats>>,
+) {
+    loop {
+        // picked up on every beat, to follow reconnections
+        let (connection, id) = match (connection.upgrade(), last_request_id.upgrade()) {
+            (Some(connection), Some(last_request_id)) => {
+                (current(&connection), next_request_id(&last_request_id))
+            }
+            _ => return,
+        };
+        let start = Instant::now();
+        let answered = connection
+            .request_with_timeout(
+                Request {
+                    id,
+                    method: "ping".to_string(),
+                    params: Vec::new(),
+                },
+                Some(period),
+            )
+            .await
+            .is_ok();
+        let rtt = start.elapsed();
+        drop(connection);
+        match health.upgrade() {
+            Some(health) => lock(&health).record(answered.then_some(rtt)),
+            None => return,
+        }
+        task::sleep(period.saturating_sub(rtt)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn snapshot() {
+        let mut stats = HealthStats::default();
+        assert_eq!(stats.snapshot(), ConnectionHealth::default());
+
+        // recorded out of order, percentiles sort them
+        for rtt in (1..=100).rev() {
+            stats.record(Some(ms(rtt)));
+        }
+        let health = stats.snapshot();
+        assert_eq!(health.last_rtt, Some(ms(1)));
+        assert_eq!(health.mean_rtt, Some(Duration::from_micros(50_500)));
+        assert_eq!(health.median_rtt, Some(ms(50)));
+        assert_eq!(health.p95_rtt, Some(ms(95)));
+        assert_eq!(health.p99_rtt, Some(ms(99)));
+
+        // only the last WINDOW round trips count, 100 to 51 are evicted
+        for rtt in 101..=150 {
+            stats.record(Some(ms(rtt)));
+        }
+        let health = stats.snapshot();
+        assert_eq!(health.last_rtt, Some(ms(150)));
+        assert_eq!(health.mean_rtt, Some(Duration::from_micros(75_500)));
+        assert_eq!(health.median_rtt, Some(ms(50)));
+        assert_eq!(health.p95_rtt, Some(ms(145)));
+        assert_eq!(health.p99_rtt, Some(ms(149)));
+    }
+
+    #[test]
+    fn failures() {
+        let mut stats = HealthStats::default();
+        stats.record(None);
+        stats.record(None);
+        let health = stats.snapshot();
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.last_success, None);
+
+        stats.record(Some(ms(3)));
+        stats.record(None);
+        let health = stats.snapshot();
+        assert_eq!(health.consecutive_failures, 1);
+        assert!(health.last_success.is_some());
+        assert_eq!(health.last_rtt, Some(ms(3)));
+    }
+}
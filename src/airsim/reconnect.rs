@@ -0,0 +1,211 @@
+use super::{current, Client};
+use crate::{
+    errors::{DisconnectReason, NetworkError, NetworkResult},
+    msgpack::Client as MsgPackClient,
+};
+use async_std::{
+    channel::{unbounded, Receiver, Sender},
+    net::{SocketAddr, ToSocketAddrs},
+    task,
+};
+use rmp_rpc::message::Request;
+use rmpv::Value;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, Weak};
+use std::time::Duration;
+
+/// How long a fresh connection gets to answer the requests restoring the session
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shortest delay between reconnection attempts
+const MIN_DELAY: Duration = Duration::from_millis(10);
+
+/// Delay between reconnection attempts, doubled after each failure.
+///
+/// Delays under 10ms are raised to 10ms, so that a zero delay does not retry in a tight loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConnectionState {
+    Disconnected(Arc<DisconnectReason>),
+    /// `attempt` starts at 1 after each disconnection
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Reconnected, with API control enabled again for every vehicle that had it
+    Connected,
+}
+
+impl Client {
+    /// Same as `connect`, but the client reconnects whenever the connection is lost, such as
+    /// when the simulator reloads its level.
+    ///
+    /// Calls made while the client is reconnecting fail with `NetworkError::Disconnected`. The
+    /// receiver yields every connection state change, it can be dropped if nobody cares.
+    pub async fn connect_with_reconnect(
+        addrs: impl ToSocketAddrs,
+        backoff: Backoff,
+    ) -> NetworkResult<(Self, Receiver<ConnectionState>)> {
+        let addrs: Vec<SocketAddr> = addrs.to_socket_addrs().await?.collect();
+        let client = Self::connect(&addrs[..]).await?;
+        let (states, receiver) = unbounded();
+        task::spawn(reconnect_forever(
+            addrs,
+            backoff,
+            Arc::downgrade(&client.connection),
+            Arc::downgrade(&client.api_control),
+            states,
+        ));
+        Ok((client, receiver))
+    }
+}
+
+/// Runs until the client is dropped, only holding weak references so it does not keep it alive.
+async fn reconnect_forever(
+    addrs: Vec<SocketAddr>,
+    backoff: Backoff,
+    connection: Weak<RwLock<Arc<MsgPackClient>>>,
+    api_control: Weak<Mutex<HashSet<String>>>,
+    states: Sender<ConnectionState>,
+) {
+    loop {
+        let closed = match connection.upgrade() {
+            Some(connection) => current(&connection).closed(),
+            None => return,
+        };
+        closed.await;
+        let reason = match connection.upgrade() {
+            Some(connection) => current(&connection).disconnect_reason(),
+            None => return,
+        };
+        let reason = reason.unwrap_or_else(|| Arc::new(DisconnectReason::Closed));
+        let _ = states.try_send(ConnectionState::Disconnected(reason));
+
+        let mut delay = backoff.initial.max(MIN_DELAY);
+        for attempt in 1.. {
+            let _ = states.try_send(ConnectionState::Reconnecting { attempt });
+            let vehicles: Vec<String> = match api_control.upgrade() {
+                Some(api_control) => api_control
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .iter()
+                    .cloned()
+                    .collect(),
+                None => return,
+            };
+            if let Ok(new_connection) = restore_session(&addrs, &vehicles).await {
+                match connection.upgrade() {
+                    Some(connection) => {
+                        *connection.write().unwrap_or_else(PoisonError::into_inner) =
+                            Arc::new(new_connection);
+                    }
+                    None => return,
+                }
+                let _ = states.try_send(ConnectionState::Connected);
+                break;
+            }
+            task::sleep(delay).await;
+            delay = (delay * 2).min(backoff.max).max(MIN_DELAY);
+        }
+    }
+}
+
+/// Connects again and gets it back to where `Client::connect` and `enable_api_control` left it.
+async fn restore_session(
+    addrs: &[SocketAddr],
+    vehicles: &[String],
+) -> NetworkResult<MsgPackClient> {
+    let connection = MsgPackClient::connect(addrs).await?;
+    // nothing else uses this connection yet, so request ids can start over
+    connection
+        .request_with_timeout(
+            Request {
+                id: 0,
+                method: "ping".to_string(),
+                params: Vec::new(),
+            },
+            Some(RESTORE_TIMEOUT),
+        )
+        .await?;
+    for (id, vehicle_name) in (1..).zip(vehicles) {
+        connection
+            .request_with_timeout(
+                Request {
+                    id,
+                    method: "enableApiControl".to_string(),
+                    params: vec![
+                        Value::Boolean(true),
+                        Value::String(vehicle_name.as_str().into()),
+                    ],
+                },
+                Some(RESTORE_TIMEOUT),
+            )
+            .await?
+            .result
+            .map_err(NetworkError::Rpc)?;
+    }
+    Ok(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server;
+
+    #[test]
+    fn reconnects_and_restores_api_control() {
+        task::block_on(async {
+            let addr = server::listen("127.0.0.1:0").await.unwrap();
+            let backoff = Backoff {
+                initial: Duration::from_millis(200),
+                max: Duration::from_secs(1),
+            };
+            let (client, states) = Client::connect_with_reconnect(addr, backoff).await.unwrap();
+            client.enable_api_control(true, "Drone1").await.unwrap();
+
+            // the mock hangs up, then refuses the first reconnection attempt
+            let dropped = client.call::<_, ()>("mockDisconnect", ()).await;
+            assert!(matches!(dropped, Err(NetworkError::Disconnected(_))));
+            assert!(matches!(
+                states.recv().await.unwrap(),
+                ConnectionState::Disconnected(_)
+            ));
+            assert!(matches!(
+                states.recv().await.unwrap(),
+                ConnectionState::Reconnecting { attempt: 1 }
+            ));
+            // still waiting for the next attempt
+            assert!(matches!(
+                client.get_car_state("").await,
+                Err(NetworkError::Disconnected(_))
+            ));
+            assert!(matches!(
+                states.recv().await.unwrap(),
+                ConnectionState::Reconnecting { attempt: 2 }
+            ));
+            assert!(matches!(
+                states.recv().await.unwrap(),
+                ConnectionState::Connected
+            ));
+
+            client.get_car_state("").await.unwrap();
+            let mut api_control: Vec<String> = client.call("mockApiControl", ()).await.unwrap();
+            // enabled again for every vehicle after reconnecting
+            api_control[2..].sort();
+            assert_eq!(api_control, ["", "Drone1", "", "Drone1"]);
+        });
+    }
+}
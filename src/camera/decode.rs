@@ -0,0 +1,148 @@
+use super::{ImageData, ImageResponse};
+use crate::errors::ImageError;
+use image::{ImageFormat, RgbImage};
+use ndarray::Array2;
+
+impl ImageResponse {
+    /// Decodes a png response, or reorders the channels of a raw BGR(A) response.
+    pub fn to_rgb_image(&self) -> Result<RgbImage, ImageError> {
+        let bytes = match &self.data {
+            ImageData::Uint8(bytes) => bytes,
+            ImageData::Float(_) => return Err(ImageError::UnexpectedFormat),
+        };
+        if self.compress {
+            return Ok(image::load_from_memory_with_format(bytes, ImageFormat::Png)?.to_rgb8());
+        }
+
+        // depending on the simulator version, raw pixels come with or without an alpha channel
+        let pixels = self.pixel_count();
+        let channels = match bytes.len() {
+            len if len == pixels * 3 => 3,
+            len if len == pixels * 4 => 4,
+            actual => {
+                return Err(ImageError::SizeMismatch {
+                    expected: pixels * 3,
+                    actual,
+                })
+            }
+        };
+        let rgb = bytes
+            .chunks_exact(channels)
+            .flat_map(|bgr| [bgr[2], bgr[1], bgr[0]])
+            .collect();
+        RgbImage::from_raw(self.width, self.height, rgb).ok_or(ImageError::SizeMismatch {
+            expected: pixels * 3,
+            actual: bytes.len() / channels * 3,
+        })
+    }
+
+    /// Depth in meters, row by row, for responses requested with `pixels_as_float`.
+    pub fn to_depth(&self) -> Result<Vec<f32>, ImageError> {
+        let depth = match &self.data {
+            ImageData::Float(depth) => depth,
+            ImageData::Uint8(_) => return Err(ImageError::UnexpectedFormat),
+        };
+        if depth.len() != self.pixel_count() {
+            return Err(ImageError::SizeMismatch {
+                expected: self.pixel_count(),
+                actual: depth.len(),
+            });
+        }
+        Ok(depth.clone())
+    }
+
+    /// Same as `to_depth`, indexed by `[row, column]`.
+    pub fn to_depth_array(&self) -> Result<Array2<f32>, ImageError> {
+        let shape = (self.height as usize, self.width as usize);
+        let depth = self.to_depth()?;
+        let actual = depth.len();
+        Array2::from_shape_vec(shape, depth).map_err(|_| ImageError::SizeMismatch {
+            expected: shape.0 * shape.1,
+            actual,
+        })
+    }
+
+    fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::ImageType;
+
+    #[test]
+    fn raw_bgr_and_bgra() {
+        let bgr = vec![1, 2, 3, 4, 5, 6];
+        let bgra = vec![1, 2, 3, 255, 4, 5, 6, 255];
+        for bytes in [bgr, bgra] {
+            let response = ImageResponse::for_test(ImageType::Scene, 2, 1, ImageData::Uint8(bytes));
+            let image = response.to_rgb_image().unwrap();
+            assert_eq!(image.into_raw(), [3, 2, 1, 6, 5, 4]);
+        }
+    }
+
+    #[test]
+    fn raw_size_mismatch() {
+        let response =
+            ImageResponse::for_test(ImageType::Scene, 2, 2, ImageData::Uint8(vec![0; 5]));
+        assert!(matches!(
+            response.to_rgb_image(),
+            Err(ImageError::SizeMismatch {
+                expected: 12,
+                actual: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn png() {
+        let image = RgbImage::from_raw(2, 1, vec![10, 20, 30, 40, 50, 60]).unwrap();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, ImageFormat::Png).unwrap();
+        let response = ImageResponse {
+            compress: true,
+            ..ImageResponse::for_test(ImageType::Scene, 2, 1, ImageData::Uint8(png.into_inner()))
+        };
+        assert_eq!(response.to_rgb_image().unwrap(), image);
+    }
+
+    #[test]
+    fn wrong_kind_of_pixels() {
+        let depth =
+            ImageResponse::for_test(ImageType::DepthPlanar, 1, 1, ImageData::Float(vec![1.]));
+        assert!(matches!(
+            depth.to_rgb_image(),
+            Err(ImageError::UnexpectedFormat)
+        ));
+        let scene = ImageResponse::for_test(ImageType::Scene, 1, 1, ImageData::Uint8(vec![0; 3]));
+        assert!(matches!(
+            scene.to_depth(),
+            Err(ImageError::UnexpectedFormat)
+        ));
+    }
+
+    #[test]
+    fn depth_array() {
+        let response = ImageResponse::for_test(
+            ImageType::DepthPlanar,
+            3,
+            2,
+            ImageData::Float(vec![1., 2., 3., 4., 5., 6.]),
+        );
+        let depth = response.to_depth_array().unwrap();
+        assert_eq!(depth.dim(), (2, 3));
+        assert_eq!(depth[[1, 0]], 4.);
+
+        let response =
+            ImageResponse::for_test(ImageType::DepthPlanar, 3, 2, ImageData::Float(vec![1.; 5]));
+        assert!(matches!(
+            response.to_depth_array(),
+            Err(ImageError::SizeMismatch {
+                expected: 6,
+                actual: 5
+            })
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use super::ImageType;
+use crate::{
+    airsim::Client,
+    errors::NetworkResult,
+    types::{GeoPoint, Pose, Vector2r, Vector3r},
+};
+use serde::Deserialize;
+
+/// Bounding box in image coordinates, in pixels.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Box2D {
+    pub min: Vector2r,
+    pub max: Vector2r,
+}
+
+/// Axis aligned bounding box, relative to the camera, in meters.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Box3D {
+    pub min: Vector3r,
+    pub max: Vector3r,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct DetectionInfo {
+    pub name: String,
+    pub geo_point: GeoPoint,
+    #[serde(rename = "box2D")]
+    pub box_2d: Box2D,
+    #[serde(rename = "box3D")]
+    pub box_3d: Box3D,
+    /// Pose of the object relative to the camera
+    pub relative_pose: Pose,
+}
+
+/// Ground truth detections, configured per camera and image type.
+///
+/// Nothing is detected until a mesh name is added to the filter.
+impl Client {
+    /// Only detects objects closer than `radius_cm` centimeters to the camera.
+    pub async fn sim_set_detection_filter_radius(
+        &self,
+        camera_name: &str,
+        image_type: ImageType,
+        radius_cm: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call(
+            "simSetDetectionFilterRadius",
+            (camera_name, image_type, radius_cm, vehicle_name, false),
+        )
+        .await
+    }
+
+    /// Detects the meshes matching `mesh_name`, which accepts `*` wildcards.
+    pub async fn sim_add_detection_filter_mesh_name(
+        &self,
+        camera_name: &str,
+        image_type: ImageType,
+        mesh_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call(
+            "simAddDetectionFilterMeshName",
+            (camera_name, image_type, mesh_name, vehicle_name, false),
+        )
+        .await
+    }
+
+    pub async fn sim_clear_detection_mesh_names(
+        &self,
+        camera_name: &str,
+        image_type: ImageType,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call(
+            "simClearDetectionMeshNames",
+            (camera_name, image_type, vehicle_name, false),
+        )
+        .await
+    }
+
+    pub async fn sim_get_detections(
+        &self,
+        camera_name: &str,
+        image_type: ImageType,
+        vehicle_name: &str,
+    ) -> NetworkResult<Vec<DetectionInfo>> {
+        self.call(
+            "simGetDetections",
+            (camera_name, image_type, vehicle_name, false),
+        )
+        .await
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{
+    airsim::Client,
+    errors::NetworkResult,
+    types::{Pose, Quaternionr, Vector3r},
+};
+use serde::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+#[cfg(feature = "images")]
+mod decode;
+pub mod detection;
+#[cfg(feature = "images")]
+pub mod point_cloud;
+#[cfg(feature = "images")]
+pub mod segmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr, Deserialize_repr)]
+#[repr(u8)]
+pub enum ImageType {
+    Scene,
+    DepthPlanar,
+    DepthPerspective,
+    DepthVis,
+    DisparityNormalized,
+    Segmentation,
+    SurfaceNormals,
+    Infrared,
+    OpticalFlow,
+    OpticalFlowVis,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageRequest {
+    pub camera_name: String,
+    pub image_type: ImageType,
+    /// Ask for one float per pixel instead of bytes, used for depth images
+    pub pixels_as_float: bool,
+    /// Ask for a png instead of raw pixels
+    pub compress: bool,
+}
+
+impl ImageRequest {
+    #[must_use]
+    pub fn new(camera_name: &str, image_type: ImageType) -> Self {
+        Self {
+            camera_name: camera_name.to_string(),
+            image_type,
+            pixels_as_float: false,
+            compress: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageData {
+    /// Raw BGR pixels, or png bytes when the image was compressed
+    Uint8(Vec<u8>),
+    Float(Vec<f32>),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "RawImageResponse")]
+pub struct ImageResponse {
+    pub data: ImageData,
+    pub camera_position: Vector3r,
+    pub camera_orientation: Quaternionr,
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    pub message: String,
+    pub pixels_as_float: bool,
+    pub compress: bool,
+    pub width: u32,
+    pub height: u32,
+    pub image_type: ImageType,
+}
+
+// the simulator always sends both buffers, only the one matching `pixels_as_float` is filled
+#[derive(Deserialize)]
+struct RawImageResponse {
+    #[serde(with = "serde_bytes")]
+    image_data_uint8: Vec<u8>,
+    image_data_float: Vec<f32>,
+    camera_position: Vector3r,
+    camera_orientation: Quaternionr,
+    time_stamp: u64,
+    message: String,
+    pixels_as_float: bool,
+    compress: bool,
+    width: u32,
+    height: u32,
+    image_type: ImageType,
+}
+
+impl From<RawImageResponse> for ImageResponse {
+    fn from(raw: RawImageResponse) -> Self {
+        Self {
+            data: if raw.pixels_as_float {
+                ImageData::Float(raw.image_data_float)
+            } else {
+                ImageData::Uint8(raw.image_data_uint8)
+            },
+            camera_position: raw.camera_position,
+            camera_orientation: raw.camera_orientation,
+            time_stamp: raw.time_stamp,
+            message: raw.message,
+            pixels_as_float: raw.pixels_as_float,
+            compress: raw.compress,
+            width: raw.width,
+            height: raw.height,
+            image_type: raw.image_type,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "images"))]
+impl ImageResponse {
+    /// An uncompressed response from a camera at the origin, looking north.
+    pub(crate) fn for_test(
+        image_type: ImageType,
+        width: u32,
+        height: u32,
+        data: ImageData,
+    ) -> Self {
+        Self {
+            pixels_as_float: matches!(data, ImageData::Float(_)),
+            data,
+            camera_position: Vector3r::default(),
+            camera_orientation: Quaternionr::new(1., 0., 0., 0.),
+            time_stamp: 0,
+            message: String::new(),
+            compress: false,
+            width,
+            height,
+            image_type,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ProjectionMatrix {
+    /// Row major
+    pub matrix: [[f32; 4]; 4],
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct CameraInfo {
+    /// World NED pose of the camera
+    pub pose: Pose,
+    /// Horizontal field of view, in degrees
+    pub fov: f32,
+    pub proj_mat: ProjectionMatrix,
+}
+
+/// Lens distortion coefficients of the Brown-Conrady model.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DistortionParams {
+    pub k1: f32,
+    pub k2: f32,
+    pub k3: f32,
+    pub p1: f32,
+    pub p2: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DistortionParam {
+    K1,
+    K2,
+    K3,
+    P1,
+    P2,
+}
+
+impl Client {
+    /// Captures one image per request, in the same order.
+    pub async fn sim_get_images(
+        &self,
+        requests: &[ImageRequest],
+        vehicle_name: &str,
+    ) -> NetworkResult<Vec<ImageResponse>> {
+        // we only talk to vehicle cameras, not external ones
+        self.call("simGetImages", (requests, vehicle_name, false))
+            .await
+    }
+
+    pub async fn sim_get_camera_info(
+        &self,
+        camera_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<CameraInfo> {
+        self.call("simGetCameraInfo", (camera_name, vehicle_name, false))
+            .await
+    }
+
+    /// Moves the camera, `pose` is relative to the vehicle.
+    pub async fn sim_set_camera_pose(
+        &self,
+        camera_name: &str,
+        pose: Pose,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call("simSetCameraPose", (camera_name, pose, vehicle_name, false))
+            .await
+    }
+
+    pub async fn sim_set_camera_fov(
+        &self,
+        camera_name: &str,
+        fov_degrees: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call(
+            "simSetCameraFov",
+            (camera_name, fov_degrees, vehicle_name, false),
+        )
+        .await
+    }
+
+    pub async fn sim_get_distortion_params(
+        &self,
+        camera_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<DistortionParams> {
+        self.call("simGetDistortionParams", (camera_name, vehicle_name, false))
+            .await
+    }
+
+    pub async fn sim_set_distortion_param(
+        &self,
+        camera_name: &str,
+        param: DistortionParam,
+        value: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.call(
+            "simSetDistortionParam",
+            (camera_name, param, value, vehicle_name, false),
+        )
+        .await
+    }
+}
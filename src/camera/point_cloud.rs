@@ -0,0 +1,220 @@
+use super::{ImageResponse, ImageType};
+use crate::{
+    errors::ImageError,
+    types::{Pose, Vector3r},
+};
+
+/// The frame points are expressed in.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frame {
+    /// x forward along the optical axis, y right, z down
+    #[default]
+    Camera,
+    /// The simulator's NED world frame, using the camera pose of the depth response
+    World,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct PointCloud {
+    pub points: Vec<Vector3r>,
+    /// One rgb color per point, empty unless the cloud was built with `colors`
+    pub colors: Vec<[u8; 3]>,
+}
+
+/// Projects a `DepthPlanar` or `DepthPerspective` float response into 3D points.
+pub struct PointCloudBuilder<'a> {
+    depth: &'a ImageResponse,
+    fov_degrees: f32,
+    frame: Frame,
+    max_depth: f32,
+    scene: Option<&'a ImageResponse>,
+}
+
+impl<'a> PointCloudBuilder<'a> {
+    /// `fov_degrees` is the horizontal field of view of the camera that took `depth`.
+    #[must_use]
+    pub fn new(depth: &'a ImageResponse, fov_degrees: f32) -> Self {
+        Self {
+            depth,
+            fov_degrees,
+            frame: Frame::default(),
+            max_depth: f32::INFINITY,
+            scene: None,
+        }
+    }
+
+    #[must_use]
+    pub fn frame(mut self, frame: Frame) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// Pixels further than `max_depth` meters, such as the sky, are dropped.
+    #[must_use]
+    pub fn max_depth(mut self, max_depth: f32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Colors each point with the matching pixel of a scene image taken by the same camera.
+    #[must_use]
+    pub fn colors(mut self, scene: &'a ImageResponse) -> Self {
+        self.scene = Some(scene);
+        self
+    }
+
+    pub fn build(self) -> Result<PointCloud, ImageError> {
+        let planar = match self.depth.image_type {
+            ImageType::DepthPlanar => true,
+            ImageType::DepthPerspective => false,
+            _ => return Err(ImageError::UnexpectedFormat),
+        };
+        let depth = self.depth.to_depth()?;
+        let scene = match self.scene {
+            Some(scene) => {
+                let rgb = scene.to_rgb_image()?;
+                if rgb.dimensions() != (self.depth.width, self.depth.height) {
+                    return Err(ImageError::SizeMismatch {
+                        expected: depth.len(),
+                        actual: rgb.width() as usize * rgb.height() as usize,
+                    });
+                }
+                Some(rgb)
+            }
+            None => None,
+        };
+
+        let width = self.depth.width as usize;
+        #[allow(clippy::cast_precision_loss)]
+        let (center_x, center_y) = (self.depth.width as f32 / 2., self.depth.height as f32 / 2.);
+        let focal = center_x / (self.fov_degrees.to_radians() / 2.).tan();
+
+        let camera_pose = Pose::new(self.depth.camera_position, self.depth.camera_orientation);
+        let mut cloud = PointCloud::default();
+        for (index, &d) in depth.iter().enumerate() {
+            if !d.is_finite() || d <= 0. || d > self.max_depth {
+                continue;
+            }
+            let (column, row) = (index % width, index / width);
+            #[allow(clippy::cast_precision_loss)]
+            let ray = Vector3r::new(
+                1.,
+                (column as f32 + 0.5 - center_x) / focal,
+                (row as f32 + 0.5 - center_y) / focal,
+            );
+            // planar depth is measured along the optical axis, perspective depth along the ray
+            let scale = if planar { d } else { d / ray.length() };
+            let point = ray * scale;
+            cloud.points.push(match self.frame {
+                Frame::Camera => point,
+                Frame::World => camera_pose.transform(point),
+            });
+            if let Some(rgb) = &scene {
+                #[allow(clippy::cast_possible_truncation)]
+                cloud
+                    .colors
+                    .push(rgb.get_pixel(column as u32, row as u32).0);
+            }
+        }
+        Ok(cloud)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{camera::ImageData, types::Quaternionr};
+
+    // at 90 degrees, the focal length of a 2 pixel wide image is 1 pixel, so the rays through the
+    // pixel centers are (1, ±0.5, ±0.5)
+    fn depth(image_type: ImageType, depth: Vec<f32>) -> ImageResponse {
+        ImageResponse::for_test(image_type, 2, 2, ImageData::Float(depth))
+    }
+
+    fn assert_close(actual: &[Vector3r], expected: &[Vector3r]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((*a - *e).length() < 1e-5, "{:?} != {:?}", a, e);
+        }
+    }
+
+    #[test]
+    fn planar() {
+        let depth = depth(ImageType::DepthPlanar, vec![2., 2., 4., 4.]);
+        let cloud = PointCloudBuilder::new(&depth, 90.).build().unwrap();
+        assert_close(
+            &cloud.points,
+            &[
+                Vector3r::new(2., -1., -1.),
+                Vector3r::new(2., 1., -1.),
+                Vector3r::new(4., -2., 2.),
+                Vector3r::new(4., 2., 2.),
+            ],
+        );
+        assert!(cloud.colors.is_empty());
+    }
+
+    #[test]
+    fn perspective() {
+        // every ray is sqrt(1.5) long
+        let d = 1.5_f32.sqrt() * 2.;
+        let depth = depth(ImageType::DepthPerspective, vec![d; 4]);
+        let cloud = PointCloudBuilder::new(&depth, 90.).build().unwrap();
+        assert_close(
+            &cloud.points,
+            &[
+                Vector3r::new(2., -1., -1.),
+                Vector3r::new(2., 1., -1.),
+                Vector3r::new(2., -1., 1.),
+                Vector3r::new(2., 1., 1.),
+            ],
+        );
+    }
+
+    #[test]
+    fn filters_and_colors() {
+        let depth = depth(ImageType::DepthPlanar, vec![2., f32::NAN, 4., 100.]);
+        // bgr pixels
+        let scene = ImageResponse::for_test(
+            ImageType::Scene,
+            2,
+            2,
+            ImageData::Uint8(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]),
+        );
+        let cloud = PointCloudBuilder::new(&depth, 90.)
+            .max_depth(10.)
+            .colors(&scene)
+            .build()
+            .unwrap();
+        assert_close(
+            &cloud.points,
+            &[Vector3r::new(2., -1., -1.), Vector3r::new(4., -2., 2.)],
+        );
+        assert_eq!(cloud.colors, [[3, 2, 1], [9, 8, 7]]);
+    }
+
+    #[test]
+    fn world_frame() {
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let depth = ImageResponse {
+            camera_position: Vector3r::new(10., 0., -1.),
+            // yawed 90 degrees, looking east
+            camera_orientation: Quaternionr::new(half, 0., 0., half),
+            ..depth(ImageType::DepthPlanar, vec![2., f32::INFINITY, 0., -1.])
+        };
+        let cloud = PointCloudBuilder::new(&depth, 90.)
+            .frame(Frame::World)
+            .build()
+            .unwrap();
+        assert_close(&cloud.points, &[Vector3r::new(11., 2., -2.)]);
+    }
+
+    #[test]
+    fn rejects_other_images() {
+        let scene = ImageResponse::for_test(ImageType::Scene, 1, 1, ImageData::Float(vec![1.]));
+        assert!(matches!(
+            PointCloudBuilder::new(&scene, 90.).build(),
+            Err(ImageError::UnexpectedFormat)
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use super::{ImageResponse, ImageType};
+use crate::errors::ImageError;
+use ndarray::Array2;
+use std::collections::HashMap;
+
+/// Maps the colors of `Segmentation` images back to the object ids set with
+/// `sim_set_segmentation_object_id`.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct SegmentationPalette {
+    colors: HashMap<u8, [u8; 3]>,
+    ids: HashMap<[u8; 3], u8>,
+}
+
+impl SegmentationPalette {
+    /// Reads the `seg_rgbs.txt` file shipped with AirSim, one `id\t[r, g, b]` entry per line.
+    pub fn parse(text: &str) -> Result<Self, ImageError> {
+        let mut palette = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (id, rgb) =
+                parse_entry(line).ok_or(ImageError::InvalidPalette { line: index + 1 })?;
+            palette.colors.insert(id, rgb);
+            palette.ids.insert(rgb, id);
+        }
+        Ok(palette)
+    }
+
+    #[must_use]
+    pub fn color(&self, object_id: u8) -> Option<[u8; 3]> {
+        self.colors.get(&object_id).copied()
+    }
+
+    #[must_use]
+    pub fn object_id(&self, rgb: [u8; 3]) -> Option<u8> {
+        self.ids.get(&rgb).copied()
+    }
+}
+
+fn parse_entry(line: &str) -> Option<(u8, [u8; 3])> {
+    let (id, rgb) = line.trim().split_once(char::is_whitespace)?;
+    let mut channels = rgb
+        .trim()
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .split(',')
+        .map(|channel| channel.trim().parse().ok());
+    let rgb = [channels.next()??, channels.next()??, channels.next()??];
+    if channels.next().is_some() {
+        return None;
+    }
+    Some((id.parse().ok()?, rgb))
+}
+
+impl ImageResponse {
+    /// Object id of each pixel of a `Segmentation` response, indexed by `[row, column]`.
+    ///
+    /// Pixels whose color is not in `palette` are `None`.
+    pub fn to_object_ids(
+        &self,
+        palette: &SegmentationPalette,
+    ) -> Result<Array2<Option<u8>>, ImageError> {
+        if self.image_type != ImageType::Segmentation {
+            return Err(ImageError::UnexpectedFormat);
+        }
+        let rgb = self.to_rgb_image()?;
+        let shape = (self.height as usize, self.width as usize);
+        let ids = rgb
+            .pixels()
+            .map(|pixel| palette.object_id(pixel.0))
+            .collect();
+        Array2::from_shape_vec(shape, ids).map_err(|_| ImageError::SizeMismatch {
+            expected: shape.0 * shape.1,
+            actual: rgb.width() as usize * rgb.height() as usize,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::ImageData;
+
+    #[test]
+    fn entries() {
+        assert_eq!(parse_entry("1\t[153, 108, 6]"), Some((1, [153, 108, 6])));
+        assert_eq!(parse_entry("  255 [0,0,0]  "), Some((255, [0, 0, 0])));
+        assert_eq!(parse_entry("1\t[153, 108, 6, 1]"), None);
+        assert_eq!(parse_entry("1\t[153, 108]"), None);
+        assert_eq!(parse_entry("1\t153, 108, 6"), None);
+        assert_eq!(parse_entry("256\t[0, 0, 0]"), None);
+        assert_eq!(parse_entry("junk"), None);
+    }
+
+    #[test]
+    fn parse_reports_the_bad_line() {
+        assert!(matches!(
+            SegmentationPalette::parse("0\t[0, 0, 0]\n\n2\t[1, 2]\n"),
+            Err(ImageError::InvalidPalette { line: 3 })
+        ));
+    }
+
+    #[test]
+    fn object_ids() {
+        let palette = SegmentationPalette::parse("0\t[0, 0, 0]\n1\t[153, 108, 6]\n").unwrap();
+        assert_eq!(palette.color(1), Some([153, 108, 6]));
+        // bgr pixels: id 1, id 0, then a color missing from the palette
+        let pixels = vec![6, 108, 153, 0, 0, 0, 1, 2, 3];
+        let response =
+            ImageResponse::for_test(ImageType::Segmentation, 3, 1, ImageData::Uint8(pixels));
+        let ids = response.to_object_ids(&palette).unwrap();
+        assert_eq!(ids.dim(), (1, 3));
+        assert_eq!(ids.into_raw_vec(), [Some(1), Some(0), None]);
+
+        let scene = ImageResponse {
+            image_type: ImageType::Scene,
+            ..response
+        };
+        assert!(matches!(
+            scene.to_object_ids(&palette),
+            Err(ImageError::UnexpectedFormat)
+        ));
+    }
+}
@@ -1,5 +1,7 @@
-use crate::airsim::{CarControls, Client};
+use crate::airsim::{CarControls, CarState, Client};
 use crate::errors::NetworkResult;
+use async_std::task;
+use std::time::Duration;
 
 pub struct Car {
     client: Client,
@@ -84,3 +86,62 @@ impl Car {
         }
     }
 }
+
+/// Drives the car in lockstep with the simulation clock.
+///
+/// Each `step` applies the controls while the simulation is paused, lets it run for a fixed
+/// number of frames and returns the state it stopped in. The simulation stays paused between
+/// steps until `resume` is called.
+///
+/// The simulator can't tell when the frames are done, so each step polls `sim_is_paused` every
+/// `poll_interval` (10ms by default) until it is: a step sends about one request per interval
+/// while the frames render.
+pub struct Stepper<'a> {
+    client: &'a Client,
+    frames: u32,
+    vehicle_name: &'a str,
+    poll_interval: Duration,
+}
+
+impl<'a> Stepper<'a> {
+    #[must_use]
+    pub fn new(client: &'a Client, frames: u32) -> Self {
+        Self {
+            client,
+            frames,
+            vehicle_name: "",
+            poll_interval: Duration::from_millis(10),
+        }
+    }
+
+    /// Drives `vehicle_name` instead of the default car.
+    #[must_use]
+    pub fn vehicle_name(mut self, vehicle_name: &'a str) -> Self {
+        self.vehicle_name = vehicle_name;
+        self
+    }
+
+    /// How long to wait between checks for the end of a step.
+    #[must_use]
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub async fn step(&self, controls: &CarControls) -> NetworkResult<CarState> {
+        self.client.sim_pause(true).await?;
+        self.client
+            .call::<_, ()>("setCarControls", (controls, self.vehicle_name))
+            .await?;
+        self.client.sim_continue_for_frames(self.frames).await?;
+        // the simulator pauses itself once the frames are rendered
+        while !self.client.sim_is_paused().await? {
+            task::sleep(self.poll_interval).await;
+        }
+        self.client.get_car_state(self.vehicle_name).await
+    }
+
+    pub async fn resume(self) -> NetworkResult<()> {
+        self.client.sim_pause(false).await
+    }
+}
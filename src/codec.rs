@@ -0,0 +1,480 @@
+use crate::errors::{NetworkError, NetworkResult};
+use rmpv::Value;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_value::Value as SerdeValue;
+
+/// Serializes `value` the way AirSim's msgpack structs expect it: structs become maps keyed by
+/// field name, sorted alphabetically since the simulator looks fields up by name. Fieldless
+/// enums are sent as their variant name, unless they derive `Serialize_repr` to be sent as
+/// integers.
+pub(crate) fn to_value<T: Serialize>(value: &T) -> NetworkResult<Value> {
+    serde_value::to_value(value)
+        .map(into_msgpack)
+        .map_err(|e| NetworkError::InvalidRequest(e.to_string()))
+}
+
+pub(crate) fn from_value<T: DeserializeOwned>(value: Value) -> NetworkResult<T> {
+    rmpv::ext::from_value(value).map_err(|e| NetworkError::InvalidResponse(e.to_string()))
+}
+
+fn into_msgpack(value: SerdeValue) -> Value {
+    match value {
+        SerdeValue::Bool(b) => Value::Boolean(b),
+        SerdeValue::U8(n) => Value::from(n),
+        SerdeValue::U16(n) => Value::from(n),
+        SerdeValue::U32(n) => Value::from(n),
+        SerdeValue::U64(n) => Value::from(n),
+        SerdeValue::I8(n) => Value::from(n),
+        SerdeValue::I16(n) => Value::from(n),
+        SerdeValue::I32(n) => Value::from(n),
+        SerdeValue::I64(n) => Value::from(n),
+        SerdeValue::F32(n) => Value::F32(n),
+        SerdeValue::F64(n) => Value::F64(n),
+        SerdeValue::Char(c) => Value::String(c.to_string().into()),
+        SerdeValue::String(s) => Value::String(s.into()),
+        SerdeValue::Bytes(bytes) => Value::Binary(bytes),
+        SerdeValue::Unit | SerdeValue::Option(None) => Value::Nil,
+        SerdeValue::Option(Some(value)) | SerdeValue::Newtype(value) => into_msgpack(*value),
+        SerdeValue::Seq(values) => Value::Array(values.into_iter().map(into_msgpack).collect()),
+        SerdeValue::Map(entries) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(key, value)| (into_msgpack(key), into_msgpack(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        airsim::CarControls,
+        camera::{
+            detection::{Box2D, Box3D, DetectionInfo},
+            CameraInfo, ProjectionMatrix,
+        },
+        camera::{
+            DistortionParam, DistortionParams, ImageData, ImageRequest, ImageResponse, ImageType,
+        },
+        multirotor::RCData,
+        multirotor::{LandedState, MultirotorState},
+        sensors::{GnssFixType, GnssReport, GpsData, LidarData},
+        server::{collision_info, kinematics_state, map, quaternionr, vector3r},
+        types::{
+            CollisionInfo, EnvironmentState, GeoPoint, KinematicsState, Pose, Quaternionr,
+            Vector2r, Vector3r,
+        },
+    };
+    use rmpv::Value;
+    use std::fmt::Debug;
+
+    fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &to_value(value).unwrap()).unwrap();
+        bytes
+    }
+
+    fn decode<T: DeserializeOwned>(mut bytes: &[u8]) -> T {
+        from_value(rmpv::decode::read_value(&mut bytes).unwrap()).unwrap()
+    }
+
+    fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T) {
+        assert_eq!(&decode::<T>(&encode(value)), value);
+    }
+
+    fn geo_point(latitude: f64, longitude: f64, altitude: f32) -> Value {
+        map(vec![
+            ("latitude", Value::F64(latitude)),
+            ("longitude", Value::F64(longitude)),
+            ("altitude", Value::F32(altitude)),
+        ])
+    }
+
+    fn pose_value(position: Value, orientation: Value) -> Value {
+        map(vec![("position", position), ("orientation", orientation)])
+    }
+
+    fn kinematics() -> KinematicsState {
+        KinematicsState {
+            position: Vector3r::new(12.5, -3.25, -0.6),
+            orientation: Quaternionr::new(0.9659, 0., 0., 0.2588),
+            linear_velocity: Vector3r::new(7.2, 1.9, 0.),
+            angular_velocity: Vector3r::new(0., 0., 0.05),
+            linear_acceleration: Vector3r::new(0.4, 0.1, 0.),
+            angular_acceleration: Vector3r::default(),
+        }
+    }
+
+    fn collision() -> CollisionInfo {
+        CollisionInfo {
+            has_collided: true,
+            normal: Vector3r::new(-1., 0., 0.),
+            impact_point: Vector3r::new(13.1, -3.25, -0.5),
+            position: Vector3r::new(12.5, -3.25, -0.6),
+            penetration_depth: 0.02,
+            time_stamp: 1_602_345_678_801_234_567,
+            object_name: "Wall_12".to_string(),
+            object_id: -1,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&Vector2r::new(3., -4.5));
+        round_trip(&kinematics());
+        round_trip(&collision());
+        round_trip(&EnvironmentState {
+            position: Vector3r::new(1., 2., -3.),
+            geo_point: GeoPoint {
+                latitude: 47.641_468,
+                longitude: -122.140_165,
+                altitude: 122.,
+            },
+            gravity: Vector3r::new(0., 0., 9.81),
+            air_pressure: 101_325.,
+            temperature: 288.15,
+            air_density: 1.225,
+        });
+    }
+
+    #[test]
+    fn kinematics_and_collision() {
+        let decoded: KinematicsState = from_value(kinematics_state()).unwrap();
+        assert_eq!(decoded, kinematics());
+        let decoded: CollisionInfo = from_value(collision_info(1_602_345_678_801_234_567)).unwrap();
+        assert_eq!(decoded, collision());
+    }
+
+    #[test]
+    fn multirotor_state() {
+        let state = map(vec![
+            ("collision", collision_info(1_602_345_678_801_234_567)),
+            ("kinematics_estimated", kinematics_state()),
+            ("gps_location", geo_point(47.641_468, -122.140_165, 122.)),
+            ("timestamp", Value::from(1_602_345_678_901_234_567_u64)),
+            ("landed_state", Value::from(1)),
+            // fields we don't expose are skipped
+            ("ready", Value::Boolean(true)),
+            ("ready_message", Value::String("".into())),
+            ("can_arm", Value::Boolean(true)),
+        ]);
+        let decoded: MultirotorState = from_value(state).unwrap();
+        assert_eq!(
+            decoded,
+            MultirotorState {
+                collision: collision(),
+                kinematics_estimated: kinematics(),
+                gps_location: GeoPoint {
+                    latitude: 47.641_468,
+                    longitude: -122.140_165,
+                    altitude: 122.,
+                },
+                timestamp: 1_602_345_678_901_234_567,
+                landed_state: LandedState::Flying,
+            }
+        );
+    }
+
+    #[test]
+    fn gps_data() {
+        let gps = map(vec![
+            ("time_stamp", Value::from(1_602_345_678_901_234_567_u64)),
+            (
+                "gnss",
+                map(vec![
+                    ("geo_point", geo_point(47.641_468, -122.140_165, 122.)),
+                    ("eph", Value::F32(0.1)),
+                    ("epv", Value::F32(0.2)),
+                    ("velocity", vector3r(1., 0., 0.)),
+                    ("fix_type", Value::from(3)),
+                    ("time_utc", Value::from(1_602_345_678_901_234_u64)),
+                ]),
+            ),
+            ("is_valid", Value::Boolean(true)),
+        ]);
+        let decoded: GpsData = from_value(gps).unwrap();
+        assert_eq!(
+            decoded,
+            GpsData {
+                time_stamp: 1_602_345_678_901_234_567,
+                gnss: GnssReport {
+                    geo_point: GeoPoint {
+                        latitude: 47.641_468,
+                        longitude: -122.140_165,
+                        altitude: 122.,
+                    },
+                    eph: 0.1,
+                    epv: 0.2,
+                    velocity: Vector3r::new(1., 0., 0.),
+                    fix_type: GnssFixType::Fix3D,
+                    time_utc: 1_602_345_678_901_234,
+                },
+                is_valid: true,
+            }
+        );
+    }
+
+    #[test]
+    fn lidar_data() {
+        let lidar = |point_cloud: Vec<f32>, segmentation: Vec<i32>| {
+            map(vec![
+                (
+                    "point_cloud",
+                    Value::Array(point_cloud.into_iter().map(Value::F32).collect()),
+                ),
+                ("time_stamp", Value::from(1_602_345_678_901_234_567_u64)),
+                (
+                    "pose",
+                    pose_value(vector3r(0., 0., -1.), quaternionr(1., 0., 0., 0.)),
+                ),
+                (
+                    "segmentation",
+                    Value::Array(segmentation.into_iter().map(Value::from).collect()),
+                ),
+            ])
+        };
+
+        let decoded: LidarData =
+            from_value(lidar(vec![1., 2., 3., 4., 5., 6.], vec![7, -1])).unwrap();
+        assert_eq!(decoded.point_cloud, [[1., 2., 3.], [4., 5., 6.]]);
+        assert_eq!(decoded.segmentation, [7, -1]);
+        assert_eq!(decoded.pose.position, Vector3r::new(0., 0., -1.));
+
+        // the simulator sends a single zero when nothing was hit
+        let empty: LidarData = from_value(lidar(vec![0.], Vec::new())).unwrap();
+        assert!(empty.point_cloud.is_empty());
+    }
+
+    #[test]
+    fn camera_info() {
+        let row = |values: [f32; 4]| Value::Array(values.iter().map(|&v| Value::F32(v)).collect());
+        let info = map(vec![
+            (
+                "pose",
+                pose_value(vector3r(0.5, 0., -0.3), quaternionr(1., 0., 0., 0.)),
+            ),
+            ("fov", Value::F32(90.)),
+            (
+                "proj_mat",
+                map(vec![(
+                    "matrix",
+                    Value::Array(vec![
+                        row([1., 0., 0., 0.]),
+                        row([0., 2., 0., 0.]),
+                        row([0., 0., 3., 4.]),
+                        row([0., 0., 1., 0.]),
+                    ]),
+                )]),
+            ),
+        ]);
+        let decoded: CameraInfo = from_value(info).unwrap();
+        assert_eq!(
+            decoded,
+            CameraInfo {
+                pose: Pose::new(Vector3r::new(0.5, 0., -0.3), Quaternionr::default()),
+                fov: 90.,
+                proj_mat: ProjectionMatrix {
+                    matrix: [
+                        [1., 0., 0., 0.],
+                        [0., 2., 0., 0.],
+                        [0., 0., 3., 4.],
+                        [0., 0., 1., 0.],
+                    ],
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn detection_info() {
+        let vector2r =
+            |x: f32, y: f32| map(vec![("x_val", Value::F32(x)), ("y_val", Value::F32(y))]);
+        let detection = map(vec![
+            ("name", Value::String("Cone_5".into())),
+            ("geo_point", geo_point(47.641_468, -122.140_165, 122.)),
+            (
+                "box2D",
+                map(vec![
+                    ("min", vector2r(10., 20.)),
+                    ("max", vector2r(30., 60.)),
+                ]),
+            ),
+            (
+                "box3D",
+                map(vec![
+                    ("min", vector3r(4., -1., -1.)),
+                    ("max", vector3r(5., 1., 1.)),
+                ]),
+            ),
+            (
+                "relative_pose",
+                pose_value(vector3r(4.5, 0., 0.), quaternionr(1., 0., 0., 0.)),
+            ),
+        ]);
+        let decoded: DetectionInfo = from_value(detection).unwrap();
+        assert_eq!(
+            decoded,
+            DetectionInfo {
+                name: "Cone_5".to_string(),
+                geo_point: GeoPoint {
+                    latitude: 47.641_468,
+                    longitude: -122.140_165,
+                    altitude: 122.,
+                },
+                box_2d: Box2D {
+                    min: Vector2r::new(10., 20.),
+                    max: Vector2r::new(30., 60.),
+                },
+                box_3d: Box3D {
+                    min: Vector3r::new(4., -1., -1.),
+                    max: Vector3r::new(5., 1., 1.),
+                },
+                relative_pose: Pose::new(Vector3r::new(4.5, 0., 0.), Quaternionr::default()),
+            }
+        );
+    }
+
+    #[test]
+    fn car_controls() {
+        let controls = CarControls {
+            throttle: 0.5,
+            steering: -0.25,
+            gear_immediate: true,
+            ..CarControls::default()
+        };
+        let expected: &[u8] = b"\x87\
+        \xa5brake\xcb\x00\x00\x00\x00\x00\x00\x00\x00\
+        \xaegear_immediate\xc3\
+        \xa9handbrake\xc2\
+        \xaeis_manual_gear\xc2\
+        \xabmanual_gear\x00\
+        \xa8steering\xcb\xbf\xd0\x00\x00\x00\x00\x00\x00\
+        \xa8throttle\xcb\x3f\xe0\x00\x00\x00\x00\x00\x00";
+        assert_eq!(encode(&controls), expected);
+    }
+
+    #[test]
+    fn rc_data() {
+        let rc = RCData {
+            timestamp: 1000,
+            pitch: 0.5,
+            roll: -0.25,
+            throttle: 0.75,
+            switch3: 1,
+            ..RCData::default()
+        };
+        let expected: &[u8] = b"\x8f\
+        \xaeis_initialized\xc3\
+        \xa8is_valid\xc3\
+        \xa5pitch\xca\x3f\x00\x00\x00\
+        \xa4roll\xca\xbe\x80\x00\x00\
+        \xa7switch1\x00\
+        \xa7switch2\x00\
+        \xa7switch3\x01\
+        \xa7switch4\x00\
+        \xa7switch5\x00\
+        \xa7switch6\x00\
+        \xa7switch7\x00\
+        \xa7switch8\x00\
+        \xa8throttle\xca\x3f\x40\x00\x00\
+        \xa9timestamp\xcd\x03\xe8\
+        \xa3yaw\xca\x00\x00\x00\x00";
+        assert_eq!(encode(&rc), expected);
+    }
+
+    #[test]
+    fn pose() {
+        let pose = Pose::new(
+            Vector3r::new(1.5, -2., 0.25),
+            Quaternionr::new(1., 0., 0., 0.),
+        );
+        let bytes: &[u8] = b"\x82\
+        \xaborientation\x84\xa5w_val\xca\x3f\x80\x00\x00\xa5x_val\xca\x00\x00\x00\x00\xa5y_val\xca\x00\x00\x00\x00\xa5z_val\xca\x00\x00\x00\x00\
+        \xa8position\x83\xa5x_val\xca\x3f\xc0\x00\x00\xa5y_val\xca\xc0\x00\x00\x00\xa5z_val\xca\x3e\x80\x00\x00";
+        assert_eq!(encode(&pose), bytes);
+        assert_eq!(decode::<Pose>(bytes), pose);
+    }
+
+    #[test]
+    fn image_request() {
+        let request = ImageRequest {
+            compress: false,
+            ..ImageRequest::new("front_center", ImageType::Segmentation)
+        };
+        let expected: &[u8] = b"\x84\
+        \xabcamera_name\xacfront_center\
+        \xa8compress\xc2\
+        \xaaimage_type\x05\
+        \xafpixels_as_float\xc2";
+        assert_eq!(encode(&request), expected);
+    }
+
+    #[test]
+    fn image_response() {
+        let scene: ImageResponse = decode(
+            b"\x8b\
+        \xb0image_data_uint8\xc4\x06\x0a\x14\x1e\x282\x3c\
+        \xb0image_data_float\x90\
+        \xafcamera_position\x83\xa5x_val\xca\x00\x00\x00\x00\xa5y_val\xca\x00\x00\x00\x00\xa5z_val\xca\xbf\xc0\x00\x00\
+        \xb2camera_orientation\x84\xa5w_val\xca\x3f\x80\x00\x00\xa5x_val\xca\x00\x00\x00\x00\xa5y_val\xca\x00\x00\x00\x00\xa5z_val\xca\x00\x00\x00\x00\
+        \xaatime_stamp\xcf\x16\x3c\xac\xe7\xc6JK\x87\
+        \xa7message\xa0\
+        \xafpixels_as_float\xc2\
+        \xa8compress\xc2\
+        \xa5width\x02\
+        \xa6height\x01\
+        \xaaimage_type\x00",
+        );
+        assert_eq!(
+            scene,
+            ImageResponse {
+                data: ImageData::Uint8(vec![10, 20, 30, 40, 50, 60]),
+                camera_position: Vector3r::new(0., 0., -1.5),
+                camera_orientation: Quaternionr::new(1., 0., 0., 0.),
+                time_stamp: 1_602_345_678_901_234_567,
+                message: String::new(),
+                pixels_as_float: false,
+                compress: false,
+                width: 2,
+                height: 1,
+                image_type: ImageType::Scene,
+            }
+        );
+
+        let depth: ImageResponse = decode(
+            b"\x8b\
+        \xb0image_data_uint8\xc4\x00\
+        \xb0image_data_float\x92\xca\x3f\xc0\x00\x00\xca\x40\x10\x00\x00\
+        \xafcamera_position\x83\xa5x_val\xca\x00\x00\x00\x00\xa5y_val\xca\x00\x00\x00\x00\xa5z_val\xca\xbf\xc0\x00\x00\
+        \xb2camera_orientation\x84\xa5w_val\xca\x3f\x80\x00\x00\xa5x_val\xca\x00\x00\x00\x00\xa5y_val\xca\x00\x00\x00\x00\xa5z_val\xca\x00\x00\x00\x00\
+        \xaatime_stamp\xcf\x16\x3c\xac\xe7\xc6JK\x87\
+        \xa7message\xa0\
+        \xafpixels_as_float\xc3\
+        \xa8compress\xc2\
+        \xa5width\x02\
+        \xa6height\x01\
+        \xaaimage_type\x01",
+        );
+        assert_eq!(depth.data, ImageData::Float(vec![1.5, 2.25]));
+        assert_eq!(depth.image_type, ImageType::DepthPlanar);
+    }
+
+    #[test]
+    fn distortion_params() {
+        let params: DistortionParams = decode(
+            b"\x95\xca\x3d\xcc\xcc\xcd\xca\xbdL\xcc\xcd\xca\x00\x00\x00\x00\xca\x3a\x83\x12o\xca\x00\x00\x00\x00",
+        );
+        assert_eq!(
+            params,
+            DistortionParams {
+                k1: 0.1,
+                k2: -0.05,
+                k3: 0.,
+                p1: 0.001,
+                p2: 0.,
+            }
+        );
+        assert_eq!(encode(&DistortionParam::K2), b"\xa2K2");
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{airsim::Client, errors::NetworkResult, types::CollisionInfo};
+use async_std::task;
+use futures::{stream, Stream};
+use std::time::Duration;
+
+impl Client {
+    /// The last collision of the vehicle, `has_collided` is false if it never hit anything.
+    pub async fn sim_get_collision_info(&self, vehicle_name: &str) -> NetworkResult<CollisionInfo> {
+        self.call("simGetCollisionInfo", (vehicle_name,)).await
+    }
+
+    /// Polls `sim_get_collision_info` every `period` and yields each new collision once.
+    ///
+    /// Collisions that happened before the first poll are ignored. Failed polls are yielded as
+    /// errors, and polling goes on after `period` until the stream is dropped.
+    pub fn collisions<'a>(
+        &'a self,
+        vehicle_name: &'a str,
+        period: Duration,
+    ) -> impl Stream<Item = NetworkResult<CollisionInfo>> + 'a {
+        // the last time stamp seen, and whether the last poll failed
+        stream::unfold(
+            (None, false),
+            move |(mut last_time_stamp, failed)| async move {
+                if failed {
+                    task::sleep(period).await;
+                }
+                loop {
+                    match self.sim_get_collision_info(vehicle_name).await {
+                        Ok(info) => match last_time_stamp {
+                            Some(last) if info.has_collided && info.time_stamp > last => {
+                                let time_stamp = info.time_stamp;
+                                return Some((Ok(info), (Some(time_stamp), false)));
+                            }
+                            Some(_) => {}
+                            None => last_time_stamp = Some(info.time_stamp),
+                        },
+                        Err(e) => return Some((Err(e), (last_time_stamp, true))),
+                    }
+                    task::sleep(period).await;
+                }
+            },
+        )
+    }
+}
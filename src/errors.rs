@@ -1,19 +1,33 @@
-use async_std::channel::RecvError;
-use rmpv::decode;
-use std::{error, fmt, io};
+use rmpv::{decode, Value};
+use std::{error, fmt, io, sync::Arc};
 
 pub type NetworkResult<T> = Result<T, NetworkError>;
 
 #[derive(Debug)]
 pub enum NetworkError {
-    Recv(RecvError),
     Io(io::Error),
+    /// The simulator answered the request with an error
+    Rpc(Value),
+    /// The request parameters could not be serialized
+    InvalidRequest(String),
+    /// The simulator answered with a value we could not make sense of
+    InvalidResponse(String),
+    /// The simulator did not answer in time
+    Timeout,
+    /// The connection is gone, this call and every later one fail with the same reason
+    Disconnected(Arc<DisconnectReason>),
 }
 
-impl From<async_std::channel::RecvError> for NetworkError {
-    fn from(e: async_std::channel::RecvError) -> Self {
-        Self::Recv(e)
-    }
+/// Why the connection to the simulator was lost
+#[derive(Debug)]
+pub enum DisconnectReason {
+    /// The simulator or the client closed the connection
+    Closed,
+    Io(io::Error),
+    /// The simulator sent bytes that are not a msgpack-rpc message
+    Decode(DecodeError),
+    /// The simulator answered a request we are not waiting for
+    UnexpectedResponse(u32),
 }
 
 impl From<io::Error> for NetworkError {
@@ -22,6 +36,28 @@ impl From<io::Error> for NetworkError {
     }
 }
 
+/// Error while turning an image response into pixels
+#[cfg(feature = "images")]
+#[derive(Debug)]
+pub enum ImageError {
+    /// The png sent by the simulator could not be decoded
+    Decode(image::ImageError),
+    /// The response does not hold the kind of pixels asked for,
+    /// such as floats when an rgb image was expected
+    UnexpectedFormat,
+    /// The pixel buffer does not match the width and height of the response
+    SizeMismatch { expected: usize, actual: usize },
+    /// A segmentation palette line is not an `id [r, g, b]` entry
+    InvalidPalette { line: usize },
+}
+
+#[cfg(feature = "images")]
+impl From<image::ImageError> for ImageError {
+    fn from(e: image::ImageError) -> Self {
+        Self::Decode(e)
+    }
+}
+
 /// Error while decoding a sequence of bytes into a `MessagePack-RPC` message
 #[derive(Debug)]
 pub enum DecodeError {
@@ -36,7 +72,11 @@ pub enum DecodeError {
 
 impl fmt::Display for DecodeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.to_string())
+        match *self {
+            DecodeError::Truncated => write!(f, "truncated message"),
+            DecodeError::Invalid => write!(f, "invalid message"),
+            DecodeError::UnknownIo(ref e) => write!(f, "unknown io error: {}", e),
+        }
     }
 }
 
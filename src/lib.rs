@@ -1,8 +1,18 @@
 pub mod airsim;
+pub mod camera;
 pub mod car;
+mod codec;
+mod collision;
 pub mod controller;
 pub mod errors;
 mod msgpack;
+pub mod multirotor;
+mod scene;
+pub mod sensors;
+#[cfg(test)]
+mod server;
+pub mod types;
+pub mod weather;
 
 #[macro_use]
 extern crate async_trait;
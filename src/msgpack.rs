@@ -1,21 +1,74 @@
-use crate::errors::NetworkResult;
-use async_std::channel::{bounded, Receiver, RecvError, Sender};
+use crate::errors::{DecodeError, DisconnectReason, NetworkError, NetworkResult};
+use async_std::channel::{bounded, Receiver, Sender};
+use async_std::future;
 use async_std::io::prelude::*;
 use async_std::net::{TcpStream, ToSocketAddrs};
-use async_std::sync::{Arc, Mutex};
 use async_std::task;
 use futures::future::FutureExt;
 use futures::select;
 use rmp_rpc::message::{Message, Notification, Request, Response};
-use std::collections::HashMap;
-use std::io::Cursor;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 
 pub struct Client {
     request_sender: Sender<Request>,
     notification_sender: Sender<Notification>,
-    pub notification_receiver: Receiver<Notification>,
-    pub request_receiver: Receiver<Request>,
-    response_channels: Arc<Mutex<HashMap<u32, Sender<Response>>>>,
+    shared: Arc<Mutex<Shared>>,
+    /// Closed by the background task once the connection is lost
+    closed: Receiver<()>,
+}
+
+#[derive(Default)]
+struct Shared {
+    response_channels: HashMap<u32, Sender<Response>>,
+    /// Requests that timed out or were dropped, oldest first, their responses are ignored if
+    /// they ever come
+    abandoned: VecDeque<u32>,
+    /// Set once the connection is lost, every call made after that fails right away
+    disconnected: Option<Arc<DisconnectReason>>,
+}
+
+impl Shared {
+    /// Whether `id` was abandoned, it can't be answered twice so it is forgotten.
+    fn forget_abandoned(&mut self, id: u32) -> bool {
+        match self.abandoned.iter().position(|&abandoned| abandoned == id) {
+            Some(index) => {
+                self.abandoned.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// How many abandoned requests are remembered, a frozen simulator would never answer them.
+///
+/// Past that, the response to the oldest one is reported as unexpected if it ever comes.
+const MAX_ABANDONED: usize = 1024;
+
+/// The lock is never held across an await, so it can be taken from `Drop` too.
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    shared.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Forgets a request once its future completes, times out or is dropped.
+struct PendingRequest<'a> {
+    shared: &'a Mutex<Shared>,
+    id: u32,
+}
+
+impl Drop for PendingRequest<'_> {
+    fn drop(&mut self) {
+        let mut shared = lock(self.shared);
+        if shared.response_channels.remove(&self.id).is_some() {
+            if shared.abandoned.len() == MAX_ABANDONED {
+                shared.abandoned.pop_front();
+            }
+            shared.abandoned.push_back(self.id);
+        }
+    }
 }
 
 enum ToDo {
@@ -25,108 +78,511 @@ enum ToDo {
 
 impl Client {
     pub async fn connect(addrs: impl ToSocketAddrs) -> NetworkResult<Self> {
-        let mut stream = TcpStream::connect(addrs).await?;
-        let response_channels = Arc::new(Mutex::new(HashMap::new()));
+        let stream = TcpStream::connect(addrs).await?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
 
         let (request_sender, request_receiver) = bounded::<Request>(1);
-        let (inner_request_sender, inner_request_receiver) = bounded::<Request>(1);
         let (notification_sender, notification_receiver) = bounded::<Notification>(1);
-        let (inner_notification_sender, inner_notification_receiver) = bounded::<Notification>(1);
-        let res_channels = Arc::clone(&response_channels);
+        let (closed_sender, closed) = bounded::<()>(1);
+        let task_shared = Arc::clone(&shared);
 
         task::spawn(async move {
-            let mut current_message: Vec<u8> = vec![];
-            let mut buf = vec![0_u8; 1024];
-            loop {
-                let to_process = select! {
-                    maybe_request = request_receiver.recv().fuse() => {
-                        if let Ok(request) = maybe_request {
-                            Some(ToDo::Send(Message::Request(request)))
-                        } else {
-                            None
-                        }
-                    },
-                    maybe_notification = notification_receiver.recv().fuse() => {
-                        if let Ok(notification) = maybe_notification {
-                            Some(ToDo::Send(Message::Notification(notification)))
-                        } else {
-                            None
-                        }
-                    },
-                    maybe_bytes_read = stream.read(&mut buf).fuse() => {
-                        if let Ok(bytes_read) = maybe_bytes_read {
-                            Some(ToDo::Receive(bytes_read))
-                        } else {
-                            None
-                        }
-                    }
+            let reason = run(
+                stream,
+                &request_receiver,
+                &notification_receiver,
+                &task_shared,
+            )
+            .await;
+            let mut shared = lock(&task_shared);
+            shared.disconnected = Some(Arc::new(reason));
+            // dropping the response senders wakes every pending request up
+            shared.response_channels.clear();
+            drop(closed_sender);
+        });
+        Ok(Self {
+            request_sender,
+            notification_sender,
+            shared,
+            closed,
+        })
+    }
+
+    /// Resolves once the connection is lost, without keeping the client alive meanwhile.
+    pub fn closed(&self) -> impl Future<Output = ()> {
+        let closed = self.closed.clone();
+        async move {
+            let _ = closed.recv().await;
+        }
+    }
+
+    pub fn disconnect_reason(&self) -> Option<Arc<DisconnectReason>> {
+        lock(&self.shared).disconnected.clone()
+    }
+
+    /// Sends `request` and waits for its response, for at most `timeout` if there is one.
+    pub async fn request_with_timeout(
+        &self,
+        request: Request,
+        timeout: Option<Duration>,
+    ) -> NetworkResult<Response> {
+        let (response_sender, response_receiver) = bounded(1);
+        {
+            let mut shared = lock(&self.shared);
+            if let Some(reason) = &shared.disconnected {
+                return Err(NetworkError::Disconnected(Arc::clone(reason)));
+            }
+            shared.response_channels.insert(request.id, response_sender);
+        }
+        let _pending = PendingRequest {
+            shared: &self.shared,
+            id: request.id,
+        };
+
+        let response = async {
+            if self.request_sender.send(request).await.is_err() {
+                return Err(self.disconnected());
+            }
+            response_receiver
+                .recv()
+                .await
+                .map_err(|_| self.disconnected())
+        };
+        match timeout {
+            Some(timeout) => future::timeout(timeout, response)
+                .await
+                .map_err(|_| NetworkError::Timeout)?,
+            None => response.await,
+        }
+    }
+
+    pub async fn notify(&self, notification: Notification) -> NetworkResult<()> {
+        if let Some(reason) = &lock(&self.shared).disconnected {
+            return Err(NetworkError::Disconnected(Arc::clone(reason)));
+        }
+        if self.notification_sender.send(notification).await.is_err() {
+            return Err(self.disconnected());
+        }
+        Ok(())
+    }
+
+    fn disconnected(&self) -> NetworkError {
+        let reason = lock(&self.shared).disconnected.clone();
+        NetworkError::Disconnected(reason.unwrap_or_else(|| Arc::new(DisconnectReason::Closed)))
+    }
+}
+
+/// Shuttles messages between the socket and the client until the connection is lost.
+async fn run(
+    mut stream: TcpStream,
+    requests: &Receiver<Request>,
+    notifications: &Receiver<Notification>,
+    shared: &Mutex<Shared>,
+) -> DisconnectReason {
+    let mut decoder = Decoder::default();
+    // images can weigh megabytes
+    let mut buf = vec![0_u8; 64 * 1024];
+    loop {
+        // the outgoing channels only close when the client is dropped
+        let to_process = select! {
+            maybe_request = requests.recv().fuse() => match maybe_request {
+                Ok(request) => ToDo::Send(Message::Request(request)),
+                Err(_) => return DisconnectReason::Closed,
+            },
+            maybe_notification = notifications.recv().fuse() => match maybe_notification {
+                Ok(notification) => ToDo::Send(Message::Notification(notification)),
+                Err(_) => return DisconnectReason::Closed,
+            },
+            maybe_bytes_read = stream.read(&mut buf).fuse() => match maybe_bytes_read {
+                Ok(0) => return DisconnectReason::Closed,
+                Ok(bytes_read) => ToDo::Receive(bytes_read),
+                Err(e) => return DisconnectReason::Io(e),
+            },
+        };
+        match to_process {
+            ToDo::Send(m) => {
+                let sent = match m.pack() {
+                    Ok(message) => stream.write_all(&message).await,
+                    Err(e) => Err(e),
                 };
-                match to_process {
-                    Some(ToDo::Send(m)) => {
-                        let message = m.pack().expect("Couldn't serialize message");
-                        stream
-                            .write_all(&message)
-                            .await
-                            .expect("couldn't send message");
-                    }
-                    Some(ToDo::Receive(n)) => {
-                        current_message.extend(&buf[..n]);
-                        let mut frame = Cursor::new(current_message.clone());
-                        match Message::decode(&mut frame) {
-                            Ok(Message::Notification(n)) => {
-                                inner_notification_sender.send(n).await;
-                            }
-                            Ok(Message::Request(r)) => {
-                                inner_request_sender.send(r).await;
-                            }
-                            Ok(Message::Response(r)) => {
-                                let mut senders = res_channels.lock().await;
-                                let sender: Sender<Response> = senders
-                                    .remove(&r.id)
-                                    .expect("Got response but no request awaiting it");
-                                sender.send(r).await;
-                            }
-                            Err(e) => {
-                                // TODO: let's figure something out!
-                                panic!(e);
+                if let Err(e) = sent {
+                    return DisconnectReason::Io(e);
+                }
+            }
+            ToDo::Receive(n) => {
+                decoder.extend(&buf[..n]);
+                loop {
+                    let message = match decoder.next_message() {
+                        Ok(Some(message)) => message,
+                        Ok(None) => break,
+                        Err(e) => return DisconnectReason::Decode(e),
+                    };
+                    match message {
+                        // the simulator only ever answers, nothing here would handle its calls
+                        Message::Notification(_) | Message::Request(_) => {}
+                        Message::Response(r) => {
+                            let sender = {
+                                let mut shared = lock(shared);
+                                match shared.response_channels.remove(&r.id) {
+                                    Some(sender) => Some(sender),
+                                    None if shared.forget_abandoned(r.id) => None,
+                                    None => return DisconnectReason::UnexpectedResponse(r.id),
+                                }
+                            };
+                            if let Some(sender) = sender {
+                                let _ = sender.send(r).await;
                             }
-                        };
-                        #[allow(clippy::cast_possible_truncation)]
-                        {
-                            let (_, remaining) =
-                                current_message.split_at(frame.position() as usize);
-                            current_message = remaining.to_vec();
                         }
                     }
-                    None => {}
                 }
             }
+        }
+    }
+}
+
+/// Splits a byte stream into msgpack-rpc messages, however the bytes were chunked.
+#[derive(Default)]
+pub(crate) struct Decoder {
+    buffer: Vec<u8>,
+    /// Start of the first message that was not decoded yet
+    position: usize,
+    /// How many bytes past `position` the next message needs at least
+    needed: usize,
+}
+
+impl Decoder {
+    pub(crate) fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.drain(..self.position);
+        self.position = 0;
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete message, or `None` until more bytes come in.
+    pub(crate) fn next_message(&mut self) -> Result<Option<Message>, DecodeError> {
+        let bytes = &self.buffer[self.position..];
+        // a large frame comes in many reads, don't scan it again until it may be complete
+        if bytes.len() < self.needed {
+            return Ok(None);
+        }
+        match frame_len(bytes)? {
+            Frame::Complete(len) => {
+                let message =
+                    Message::decode(&mut &bytes[..len]).map_err(|_| DecodeError::Invalid)?;
+                self.position += len;
+                self.needed = 0;
+                Ok(Some(message))
+            }
+            Frame::Partial { needed } => {
+                self.needed = needed;
+                Ok(None)
+            }
+        }
+    }
+}
+
+enum Frame {
+    Complete(usize),
+    /// At least `needed` bytes are required to go any further
+    Partial {
+        needed: usize,
+    },
+}
+
+/// Finds where the msgpack value at the start of `bytes` ends, by only reading the headers.
+fn frame_len(bytes: &[u8]) -> Result<Frame, DecodeError> {
+    let mut position = 0;
+    // values left to skip, arrays and maps add their elements
+    let mut remaining: usize = 1;
+    while remaining > 0 {
+        let marker = match bytes.get(position) {
+            Some(&marker) => marker,
+            None => {
+                return Ok(Frame::Partial {
+                    needed: position + 1,
+                })
+            }
+        };
+        // how many bytes hold the length that follows the marker, and what comes after them
+        let (length_size, body) = match marker {
+            0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => (0, Body::Bytes(0)),
+            0x80..=0x8f => (0, Body::Values(2 * usize::from(marker & 0x0f))),
+            0x90..=0x9f => (0, Body::Values(usize::from(marker & 0x0f))),
+            0xa0..=0xbf => (0, Body::Bytes(usize::from(marker & 0x1f))),
+            0xca => (0, Body::Bytes(4)),
+            0xcb => (0, Body::Bytes(8)),
+            0xcc..=0xcf => (0, Body::Bytes(1 << (marker - 0xcc))),
+            0xd0..=0xd3 => (0, Body::Bytes(1 << (marker - 0xd0))),
+            // fixext, with its type first
+            0xd4..=0xd8 => (0, Body::Bytes(1 + (1 << (marker - 0xd4)))),
+            0xc4 | 0xd9 => (1, Body::LengthBytes(0)),
+            0xc5 | 0xda => (2, Body::LengthBytes(0)),
+            0xc6 | 0xdb => (4, Body::LengthBytes(0)),
+            0xc7 => (1, Body::LengthBytes(1)),
+            0xc8 => (2, Body::LengthBytes(1)),
+            0xc9 => (4, Body::LengthBytes(1)),
+            0xdc => (2, Body::LengthValues(1)),
+            0xdd => (4, Body::LengthValues(1)),
+            0xde => (2, Body::LengthValues(2)),
+            0xdf => (4, Body::LengthValues(2)),
+            0xc1 => return Err(DecodeError::Invalid),
+        };
+        let length_end = position + 1 + length_size;
+        let length = match bytes.get(position + 1..length_end) {
+            Some(length) => length.iter().fold(0, |len, &b| len << 8 | usize::from(b)),
+            None => return Ok(Frame::Partial { needed: length_end }),
+        };
+        let (skipped, values) = match body {
+            Body::Bytes(len) => (len, 0),
+            Body::Values(count) => (0, count),
+            Body::LengthBytes(extra) => (length + extra, 0),
+            Body::LengthValues(per_element) => (0, length * per_element),
+        };
+        position = length_end + skipped;
+        if position > bytes.len() {
+            return Ok(Frame::Partial { needed: position });
+        }
+        remaining = remaining - 1 + values;
+    }
+    Ok(Frame::Complete(position))
+}
+
+enum Body {
+    Bytes(usize),
+    Values(usize),
+    /// As many bytes as the length says, plus some
+    LengthBytes(usize),
+    /// As many elements as the length says, each made of this many values
+    LengthValues(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::net::TcpListener;
+
+    fn messages() -> Vec<Message> {
+        vec![
+            Message::Request(Request {
+                id: 1,
+                method: "getCarState".to_string(),
+                params: vec![rmpv::Value::String("".into())],
+            }),
+            Message::Response(Response {
+                id: 1,
+                result: Ok(rmpv::Value::Binary(vec![7; 300])),
+            }),
+            Message::Notification(Notification {
+                method: "reset".to_string(),
+                params: Vec::new(),
+            }),
+            Message::Response(Response {
+                id: 2,
+                result: Err(rmpv::Value::String("method not implemented".into())),
+            }),
+            // every kind of msgpack header
+            Message::Response(Response {
+                id: 3,
+                result: Ok(rmpv::Value::Array(vec![
+                    rmpv::Value::Nil,
+                    rmpv::Value::Boolean(true),
+                    rmpv::Value::from(-5),
+                    rmpv::Value::from(300),
+                    rmpv::Value::from(-100_000),
+                    rmpv::Value::from(u64::MAX),
+                    rmpv::Value::F32(1.5),
+                    rmpv::Value::F64(-2.5),
+                    rmpv::Value::String("a".repeat(40).into()),
+                    rmpv::Value::Array((0..20).map(rmpv::Value::from).collect()),
+                    rmpv::Value::Map(
+                        (0..20)
+                            .map(|i| (rmpv::Value::from(i), rmpv::Value::Nil))
+                            .collect(),
+                    ),
+                    rmpv::Value::Ext(1, vec![1, 2, 3, 4]),
+                    rmpv::Value::Ext(2, vec![1, 2, 3]),
+                ])),
+            }),
+        ]
+    }
+
+    fn pack_all(messages: &[Message]) -> Vec<u8> {
+        messages.iter().flat_map(|m| m.pack().unwrap()).collect()
+    }
+
+    fn decode_all(decoder: &mut Decoder) -> Vec<Message> {
+        let mut decoded = Vec::new();
+        while let Some(message) = decoder.next_message().unwrap() {
+            decoded.push(message);
+        }
+        decoded
+    }
+
+    #[test]
+    fn abandoned_requests_are_capped() {
+        let shared = Mutex::new(Shared::default());
+        for id in 0..2000 {
+            let (sender, _) = bounded(1);
+            lock(&shared).response_channels.insert(id, sender);
+            drop(PendingRequest {
+                shared: &shared,
+                id,
+            });
+        }
+        let mut shared = lock(&shared);
+        assert_eq!(shared.abandoned.len(), MAX_ABANDONED);
+        assert!(!shared.forget_abandoned(0));
+        assert!(shared.forget_abandoned(1999));
+        assert!(!shared.forget_abandoned(1999));
+    }
+
+    #[test]
+    fn decodes_byte_by_byte() {
+        let mut decoder = Decoder::default();
+        let mut decoded = Vec::new();
+        for byte in pack_all(&messages()) {
+            decoder.extend(&[byte]);
+            decoded.extend(decode_all(&mut decoder));
+        }
+        assert_eq!(decoded, messages());
+    }
+
+    #[test]
+    fn decodes_concatenated_frames() {
+        let bytes = pack_all(&messages());
+        let mut decoder = Decoder::default();
+        decoder.extend(&bytes);
+        assert_eq!(decode_all(&mut decoder), messages());
+
+        // a read ending in the middle of a frame
+        let split = bytes.len() - 10;
+        let mut decoder = Decoder::default();
+        decoder.extend(&bytes[..split]);
+        assert_eq!(decode_all(&mut decoder), messages()[..4]);
+        decoder.extend(&bytes[split..]);
+        assert_eq!(decode_all(&mut decoder), messages()[4..]);
+    }
+
+    #[test]
+    fn rejects_invalid_frames() {
+        // 0xc1 is never used by msgpack, the others are not msgpack-rpc messages
+        for bytes in [&[0x94, 0x01, 0xc1][..], b"\xa1x", &[0x92, 0x01, 0x02]] {
+            let mut decoder = Decoder::default();
+            decoder.extend(bytes);
+            assert!(decoder.next_message().is_err());
+        }
+    }
+
+    /// Accepts one connection, waits for a request, then writes `answer` and hangs up.
+    async fn serve_once(answer: &'static [u8]) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        task::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0_u8; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            stream.write_all(answer).await.unwrap();
         });
-        Ok(Self {
-            request_sender,
-            notification_sender,
-            notification_receiver: inner_notification_receiver,
-            request_receiver: inner_request_receiver,
-            response_channels,
-        })
+        addr
     }
 
-    pub async fn request(&self, request: Request) -> Result<Response, RecvError> {
-        let (response_sender, response_receiver) = bounded(1);
+    fn ping(id: u32) -> Request {
+        Request {
+            id,
+            method: "ping".to_string(),
+            params: Vec::new(),
+        }
+    }
 
-        // TODO: check if there was something
-        let _ = self
-            .response_channels
-            .lock()
-            .await
-            .insert(request.id, response_sender);
+    async fn assert_disconnected(client: &Client, reason: fn(&DisconnectReason) -> bool) {
+        for result in [
+            client.request_with_timeout(ping(1), None).await.map(drop),
+            client
+                .notify(Notification {
+                    method: "reset".to_string(),
+                    params: Vec::new(),
+                })
+                .await,
+        ] {
+            match result {
+                Err(NetworkError::Disconnected(r)) => assert!(reason(&r), "{:?}", r),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
 
-        self.request_sender.send(request).await;
-        response_receiver.recv().await
+    #[test]
+    fn closed_connection_fails_pending_and_later_calls() {
+        task::block_on(async {
+            let client = Client::connect(serve_once(&[]).await).await.unwrap();
+            let pending = client.request_with_timeout(ping(0), None).await;
+            assert!(matches!(
+                pending,
+                Err(NetworkError::Disconnected(ref r)) if matches!(**r, DisconnectReason::Closed)
+            ));
+            assert_disconnected(&client, |r| matches!(r, DisconnectReason::Closed)).await;
+        });
     }
 
-    pub async fn notify(&self, notification: Notification) -> Result<(), RecvError> {
-        self.notification_sender.send(notification).await;
-        Ok(())
+    #[test]
+    fn bad_bytes_fail_pending_and_later_calls() {
+        task::block_on(async {
+            let client = Client::connect(serve_once(&[0xc1]).await).await.unwrap();
+            let pending = client.request_with_timeout(ping(0), None).await;
+            assert!(matches!(
+                pending,
+                Err(NetworkError::Disconnected(ref r)) if matches!(**r, DisconnectReason::Decode(_))
+            ));
+            client.closed().await;
+            assert_disconnected(&client, |r| matches!(r, DisconnectReason::Decode(_))).await;
+        });
+    }
+
+    #[test]
+    fn calls_from_the_server_are_ignored() {
+        task::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            task::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut decoder = Decoder::default();
+                let mut buf = [0_u8; 1024];
+                let request = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    decoder.extend(&buf[..n]);
+                    if let Some(Message::Request(request)) = decoder.next_message().unwrap() {
+                        break request;
+                    }
+                };
+                for _ in 0..3 {
+                    let notification = Message::Notification(Notification {
+                        method: "tick".to_string(),
+                        params: Vec::new(),
+                    });
+                    stream
+                        .write_all(&notification.pack().unwrap())
+                        .await
+                        .unwrap();
+                }
+                let response = Message::Response(Response {
+                    id: request.id,
+                    result: Ok(rmpv::Value::Nil),
+                });
+                stream.write_all(&response.pack().unwrap()).await.unwrap();
+                // keep the connection open until the client is done
+                let _ = stream.read(&mut buf).await;
+            });
+
+            let client = Client::connect(addr).await.unwrap();
+            let request = Request {
+                id: 0,
+                method: "ping".to_string(),
+                params: Vec::new(),
+            };
+            let response = client
+                .request_with_timeout(request, Some(Duration::from_secs(5)))
+                .await
+                .unwrap();
+            assert_eq!(response.id, 0);
+        });
     }
 }
@@ -0,0 +1,348 @@
+use super::MultirotorClient;
+use crate::errors::NetworkResult;
+use serde::Serialize;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct PidGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+impl PidGains {
+    #[must_use]
+    pub fn new(kp: f32, ki: f32, kd: f32) -> Self {
+        Self { kp, ki, kd }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AngleRateControllerGains {
+    pub roll: PidGains,
+    pub pitch: PidGains,
+    pub yaw: PidGains,
+}
+
+impl Default for AngleRateControllerGains {
+    fn default() -> Self {
+        Self {
+            roll: PidGains::new(0.25, 0., 0.),
+            pitch: PidGains::new(0.25, 0., 0.),
+            yaw: PidGains::new(0.25, 0., 0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AngleLevelControllerGains {
+    pub roll: PidGains,
+    pub pitch: PidGains,
+    pub yaw: PidGains,
+}
+
+impl Default for AngleLevelControllerGains {
+    fn default() -> Self {
+        Self {
+            roll: PidGains::new(2.5, 0., 0.),
+            pitch: PidGains::new(2.5, 0., 0.),
+            yaw: PidGains::new(2.5, 0., 0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VelocityControllerGains {
+    pub x: PidGains,
+    pub y: PidGains,
+    pub z: PidGains,
+}
+
+impl Default for VelocityControllerGains {
+    fn default() -> Self {
+        Self {
+            x: PidGains::new(0.2, 0., 0.),
+            y: PidGains::new(0.2, 0., 0.),
+            z: PidGains::new(2., 2., 0.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionControllerGains {
+    pub x: PidGains,
+    pub y: PidGains,
+    pub z: PidGains,
+}
+
+impl Default for PositionControllerGains {
+    fn default() -> Self {
+        Self {
+            x: PidGains::new(0.25, 0., 0.),
+            y: PidGains::new(0.25, 0., 0.),
+            z: PidGains::new(0.25, 0., 0.),
+        }
+    }
+}
+
+/// Remote control input, sticks range from -1 to 1.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RCData {
+    pub timestamp: u64,
+    pub pitch: f32,
+    pub roll: f32,
+    pub throttle: f32,
+    pub yaw: f32,
+    pub switch1: u32,
+    pub switch2: u32,
+    pub switch3: u32,
+    pub switch4: u32,
+    pub switch5: u32,
+    pub switch6: u32,
+    pub switch7: u32,
+    pub switch8: u32,
+    pub is_initialized: bool,
+    pub is_valid: bool,
+}
+
+impl Default for RCData {
+    fn default() -> Self {
+        Self {
+            timestamp: 0,
+            pitch: 0.,
+            roll: 0.,
+            throttle: 0.,
+            yaw: 0.,
+            switch1: 0,
+            switch2: 0,
+            switch3: 0,
+            switch4: 0,
+            switch5: 0,
+            switch6: 0,
+            switch7: 0,
+            switch8: 0,
+            is_initialized: true,
+            is_valid: true,
+        }
+    }
+}
+
+/// Low level commands, angles are in radians and rates in radians per second.
+///
+/// Like the python client, pitch and yaw are negated before being sent, so that positive pitch
+/// tilts the nose up and positive yaw turns clockwise seen from above.
+impl MultirotorClient {
+    /// Drives each motor directly, PWM values range from 0 to 1.
+    pub async fn move_by_motor_pwms(
+        &self,
+        front_right_pwm: f32,
+        rear_left_pwm: f32,
+        front_left_pwm: f32,
+        rear_right_pwm: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.call(
+            "moveByMotorPWMs",
+            (
+                front_right_pwm,
+                rear_left_pwm,
+                front_left_pwm,
+                rear_right_pwm,
+                duration,
+                vehicle_name,
+            ),
+        )
+        .await
+    }
+
+    pub async fn move_by_roll_pitch_yaw_z(
+        &self,
+        roll: f32,
+        pitch: f32,
+        yaw: f32,
+        z: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.attitude_command(
+            "moveByRollPitchYawZ",
+            [roll, pitch, yaw, z],
+            duration,
+            vehicle_name,
+        )
+        .await
+    }
+
+    /// `throttle` ranges from 0 to 1.
+    pub async fn move_by_roll_pitch_yaw_throttle(
+        &self,
+        roll: f32,
+        pitch: f32,
+        yaw: f32,
+        throttle: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.attitude_command(
+            "moveByRollPitchYawThrottle",
+            [roll, pitch, yaw, throttle],
+            duration,
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn move_by_roll_pitch_yawrate_z(
+        &self,
+        roll: f32,
+        pitch: f32,
+        yaw_rate: f32,
+        z: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.attitude_command(
+            "moveByRollPitchYawrateZ",
+            [roll, pitch, yaw_rate, z],
+            duration,
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn move_by_roll_pitch_yawrate_throttle(
+        &self,
+        roll: f32,
+        pitch: f32,
+        yaw_rate: f32,
+        throttle: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.attitude_command(
+            "moveByRollPitchYawrateThrottle",
+            [roll, pitch, yaw_rate, throttle],
+            duration,
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn move_by_angle_rates_z(
+        &self,
+        roll_rate: f32,
+        pitch_rate: f32,
+        yaw_rate: f32,
+        z: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.attitude_command(
+            "moveByAngleRatesZ",
+            [roll_rate, pitch_rate, yaw_rate, z],
+            duration,
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn move_by_angle_rates_throttle(
+        &self,
+        roll_rate: f32,
+        pitch_rate: f32,
+        yaw_rate: f32,
+        throttle: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.attitude_command(
+            "moveByAngleRatesThrottle",
+            [roll_rate, pitch_rate, yaw_rate, throttle],
+            duration,
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn move_by_rc(&self, rc_data: &RCData, vehicle_name: &str) -> NetworkResult<()> {
+        self.call("moveByRC", (rc_data, vehicle_name)).await
+    }
+
+    pub async fn set_angle_rate_controller_gains(
+        &self,
+        gains: &AngleRateControllerGains,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.set_controller_gains(
+            "setAngleRateControllerGains",
+            [gains.roll, gains.pitch, gains.yaw],
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn set_angle_level_controller_gains(
+        &self,
+        gains: &AngleLevelControllerGains,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.set_controller_gains(
+            "setAngleLevelControllerGains",
+            [gains.roll, gains.pitch, gains.yaw],
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn set_velocity_controller_gains(
+        &self,
+        gains: &VelocityControllerGains,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.set_controller_gains(
+            "setVelocityControllerGains",
+            [gains.x, gains.y, gains.z],
+            vehicle_name,
+        )
+        .await
+    }
+
+    pub async fn set_position_controller_gains(
+        &self,
+        gains: &PositionControllerGains,
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        self.set_controller_gains(
+            "setPositionControllerGains",
+            [gains.x, gains.y, gains.z],
+            vehicle_name,
+        )
+        .await
+    }
+
+    async fn attitude_command(
+        &self,
+        method: &str,
+        [roll, pitch, yaw, fourth]: [f32; 4],
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.call(method, (roll, -pitch, -yaw, fourth, duration, vehicle_name))
+            .await
+    }
+
+    async fn set_controller_gains(
+        &self,
+        method: &str,
+        gains: [PidGains; 3],
+        vehicle_name: &str,
+    ) -> NetworkResult<()> {
+        // the simulator takes gains as three lists: every kp, every ki, then every kd
+        let list = |gain: fn(&PidGains) -> f32| gains.iter().map(gain).collect::<Vec<_>>();
+        self.call(
+            method,
+            (list(|g| g.kp), list(|g| g.ki), list(|g| g.kd), vehicle_name),
+        )
+        .await
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    airsim::Client,
+    errors::NetworkResult,
+    types::{CollisionInfo, GeoPoint, KinematicsState},
+};
+use async_std::net::ToSocketAddrs;
+use serde::Deserialize;
+use serde_repr::Deserialize_repr;
+use std::ops::{Deref, DerefMut};
+
+mod control;
+mod motion;
+
+pub use control::{
+    AngleLevelControllerGains, AngleRateControllerGains, PidGains, PositionControllerGains, RCData,
+    VelocityControllerGains,
+};
+pub use motion::{DrivetrainType, MoveCommand, YawMode};
+
+/// A client for multirotor vehicles.
+///
+/// It derefs to `airsim::Client`, so every vehicle agnostic call is available too.
+pub struct MultirotorClient {
+    client: Client,
+}
+
+impl MultirotorClient {
+    pub async fn connect(addrs: impl ToSocketAddrs) -> NetworkResult<Self> {
+        Ok(Self {
+            client: Client::connect(addrs).await?,
+        })
+    }
+
+    pub async fn arm_disarm(&self, arm: bool, vehicle_name: &str) -> NetworkResult<bool> {
+        self.call("armDisarm", (arm, vehicle_name)).await
+    }
+
+    /// Takes off and waits until the vehicle reaches its takeoff altitude.
+    pub async fn takeoff(&self, timeout_sec: f32, vehicle_name: &str) -> NetworkResult<bool> {
+        self.timed_command("takeoff", timeout_sec, vehicle_name)
+            .await
+    }
+
+    pub async fn land(&self, timeout_sec: f32, vehicle_name: &str) -> NetworkResult<bool> {
+        self.timed_command("land", timeout_sec, vehicle_name).await
+    }
+
+    pub async fn go_home(&self, timeout_sec: f32, vehicle_name: &str) -> NetworkResult<bool> {
+        self.timed_command("goHome", timeout_sec, vehicle_name)
+            .await
+    }
+
+    pub async fn hover(&self, vehicle_name: &str) -> NetworkResult<bool> {
+        self.call("hover", (vehicle_name,)).await
+    }
+
+    pub async fn get_multirotor_state(&self, vehicle_name: &str) -> NetworkResult<MultirotorState> {
+        self.call("getMultirotorState", (vehicle_name,)).await
+    }
+
+    pub async fn get_rotor_states(&self, vehicle_name: &str) -> NetworkResult<RotorStates> {
+        self.call("getRotorStates", (vehicle_name,)).await
+    }
+
+    async fn timed_command(
+        &self,
+        method: &str,
+        timeout_sec: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.call(method, (timeout_sec, vehicle_name)).await
+    }
+}
+
+impl Deref for MultirotorClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl DerefMut for MultirotorClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.client
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize_repr)]
+#[repr(u8)]
+pub enum LandedState {
+    #[default]
+    Landed,
+    Flying,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct MultirotorState {
+    pub collision: CollisionInfo,
+    pub kinematics_estimated: KinematicsState,
+    pub gps_location: GeoPoint,
+    /// Simulation time in nanoseconds
+    pub timestamp: u64,
+    pub landed_state: LandedState,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct RotorParameters {
+    pub thrust: f32,
+    pub torque_scaler: f32,
+    pub speed: f32,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct RotorStates {
+    /// Simulation time in nanoseconds
+    pub timestamp: u64,
+    pub rotors: Vec<RotorParameters>,
+}
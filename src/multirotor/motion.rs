@@ -0,0 +1,297 @@
+use super::MultirotorClient;
+use crate::{airsim::Client, errors::NetworkResult, types::Vector3r};
+use serde::Serialize;
+use serde_repr::Serialize_repr;
+
+/// How the vehicle orients itself while it moves.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum DrivetrainType {
+    /// Yaw is controlled independently of the direction of travel, through `YawMode`
+    #[default]
+    MaxDegreeOfFreedom,
+    /// The vehicle always faces the direction of travel
+    ForwardOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct YawMode {
+    pub is_rate: bool,
+    /// Yaw angle in degrees, or yaw rate in degrees per second when `is_rate` is set
+    pub yaw_or_rate: f32,
+}
+
+impl YawMode {
+    #[must_use]
+    pub fn angle(degrees: f32) -> Self {
+        Self {
+            is_rate: false,
+            yaw_or_rate: degrees,
+        }
+    }
+
+    #[must_use]
+    pub fn rate(degrees_per_second: f32) -> Self {
+        Self {
+            is_rate: true,
+            yaw_or_rate: degrees_per_second,
+        }
+    }
+}
+
+impl Default for YawMode {
+    fn default() -> Self {
+        Self::rate(0.)
+    }
+}
+
+enum Motion {
+    ToPosition {
+        position: Vector3r,
+        velocity: f32,
+    },
+    OnPath {
+        path: Vec<Vector3r>,
+        velocity: f32,
+    },
+    ToZ {
+        z: f32,
+        velocity: f32,
+    },
+    ByVelocity {
+        velocity: Vector3r,
+        duration: f32,
+    },
+    ByVelocityZ {
+        vx: f32,
+        vy: f32,
+        z: f32,
+        duration: f32,
+    },
+}
+
+/// A pending motion command, sent to the simulator by `send`.
+///
+/// Options a command does not support are ignored: `drivetrain` is not used by `move_to_z`,
+/// and `timeout_sec` / `lookahead` / `adaptive_lookahead` are not used by the velocity commands.
+pub struct MoveCommand<'a> {
+    client: &'a Client,
+    motion: Motion,
+    timeout_sec: f32,
+    drivetrain: DrivetrainType,
+    yaw_mode: YawMode,
+    lookahead: f32,
+    adaptive_lookahead: f32,
+    vehicle_name: String,
+}
+
+impl<'a> MoveCommand<'a> {
+    fn new(client: &'a Client, motion: Motion) -> Self {
+        Self {
+            client,
+            motion,
+            timeout_sec: 3e38,
+            drivetrain: DrivetrainType::default(),
+            yaw_mode: YawMode::default(),
+            lookahead: -1.,
+            adaptive_lookahead: 1.,
+            vehicle_name: String::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn timeout_sec(mut self, timeout_sec: f32) -> Self {
+        self.timeout_sec = timeout_sec;
+        self
+    }
+
+    #[must_use]
+    pub fn drivetrain(mut self, drivetrain: DrivetrainType) -> Self {
+        self.drivetrain = drivetrain;
+        self
+    }
+
+    #[must_use]
+    pub fn yaw_mode(mut self, yaw_mode: YawMode) -> Self {
+        self.yaw_mode = yaw_mode;
+        self
+    }
+
+    /// Distance ahead on the path the carrot is placed at, `-1` lets the simulator decide
+    #[must_use]
+    pub fn lookahead(mut self, lookahead: f32) -> Self {
+        self.lookahead = lookahead;
+        self
+    }
+
+    #[must_use]
+    pub fn adaptive_lookahead(mut self, adaptive_lookahead: f32) -> Self {
+        self.adaptive_lookahead = adaptive_lookahead;
+        self
+    }
+
+    #[must_use]
+    pub fn vehicle_name(mut self, vehicle_name: &str) -> Self {
+        self.vehicle_name = vehicle_name.to_string();
+        self
+    }
+
+    /// Sends the command and waits until the simulator reports it done.
+    pub async fn send(self) -> NetworkResult<bool> {
+        let vehicle_name = self.vehicle_name.as_str();
+        match self.motion {
+            Motion::ToPosition { position, velocity } => {
+                self.client
+                    .call(
+                        "moveToPosition",
+                        (
+                            position.x,
+                            position.y,
+                            position.z,
+                            velocity,
+                            self.timeout_sec,
+                            self.drivetrain,
+                            self.yaw_mode,
+                            self.lookahead,
+                            self.adaptive_lookahead,
+                            vehicle_name,
+                        ),
+                    )
+                    .await
+            }
+            Motion::OnPath { ref path, velocity } => {
+                self.client
+                    .call(
+                        "moveOnPath",
+                        (
+                            path,
+                            velocity,
+                            self.timeout_sec,
+                            self.drivetrain,
+                            self.yaw_mode,
+                            self.lookahead,
+                            self.adaptive_lookahead,
+                            vehicle_name,
+                        ),
+                    )
+                    .await
+            }
+            Motion::ToZ { z, velocity } => {
+                self.client
+                    .call(
+                        "moveToZ",
+                        (
+                            z,
+                            velocity,
+                            self.timeout_sec,
+                            self.yaw_mode,
+                            self.lookahead,
+                            self.adaptive_lookahead,
+                            vehicle_name,
+                        ),
+                    )
+                    .await
+            }
+            Motion::ByVelocity { velocity, duration } => {
+                self.client
+                    .call(
+                        "moveByVelocity",
+                        (
+                            velocity.x,
+                            velocity.y,
+                            velocity.z,
+                            duration,
+                            self.drivetrain,
+                            self.yaw_mode,
+                            vehicle_name,
+                        ),
+                    )
+                    .await
+            }
+            Motion::ByVelocityZ {
+                vx,
+                vy,
+                z,
+                duration,
+            } => {
+                self.client
+                    .call(
+                        "moveByVelocityZ",
+                        (
+                            vx,
+                            vy,
+                            z,
+                            duration,
+                            self.drivetrain,
+                            self.yaw_mode,
+                            vehicle_name,
+                        ),
+                    )
+                    .await
+            }
+        }
+    }
+}
+
+impl MultirotorClient {
+    /// Flies to `position` (NED, in meters) at `velocity` meters per second.
+    #[must_use]
+    pub fn move_to_position(&self, position: Vector3r, velocity: f32) -> MoveCommand<'_> {
+        MoveCommand::new(&self.client, Motion::ToPosition { position, velocity })
+    }
+
+    /// Flies through every point of `path` (NED, in meters) at `velocity` meters per second.
+    #[must_use]
+    pub fn move_on_path(&self, path: Vec<Vector3r>, velocity: f32) -> MoveCommand<'_> {
+        MoveCommand::new(&self.client, Motion::OnPath { path, velocity })
+    }
+
+    /// Climbs or descends to altitude `z` (NED, so negative is up).
+    #[must_use]
+    pub fn move_to_z(&self, z: f32, velocity: f32) -> MoveCommand<'_> {
+        MoveCommand::new(&self.client, Motion::ToZ { z, velocity })
+    }
+
+    /// Flies at `velocity` (world frame, meters per second) for `duration` seconds.
+    #[must_use]
+    pub fn move_by_velocity(&self, velocity: Vector3r, duration: f32) -> MoveCommand<'_> {
+        MoveCommand::new(&self.client, Motion::ByVelocity { velocity, duration })
+    }
+
+    /// Flies horizontally at (`vx`, `vy`) for `duration` seconds while holding altitude `z`.
+    #[must_use]
+    pub fn move_by_velocity_z(&self, vx: f32, vy: f32, z: f32, duration: f32) -> MoveCommand<'_> {
+        MoveCommand::new(
+            &self.client,
+            Motion::ByVelocityZ {
+                vx,
+                vy,
+                z,
+                duration,
+            },
+        )
+    }
+
+    /// Turns to `yaw` degrees, until within `margin` degrees of it.
+    pub async fn rotate_to_yaw(
+        &self,
+        yaw: f32,
+        timeout_sec: f32,
+        margin: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.call("rotateToYaw", (yaw, timeout_sec, margin, vehicle_name))
+            .await
+    }
+
+    /// Turns at `yaw_rate` degrees per second for `duration` seconds.
+    pub async fn rotate_by_yaw_rate(
+        &self,
+        yaw_rate: f32,
+        duration: f32,
+        vehicle_name: &str,
+    ) -> NetworkResult<bool> {
+        self.call("rotateByYawRate", (yaw_rate, duration, vehicle_name))
+            .await
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    airsim::Client,
+    errors::NetworkResult,
+    types::{Pose, Vector3r},
+};
+
+impl Client {
+    /// Names of the scene objects matching `name_regex`, `".*"` lists all of them.
+    pub async fn sim_list_scene_objects(&self, name_regex: &str) -> NetworkResult<Vec<String>> {
+        self.call("simListSceneObjects", (name_regex,)).await
+    }
+
+    /// The pose of an object in world NED coordinates, with NaN values if it does not exist.
+    pub async fn sim_get_object_pose(&self, object_name: &str) -> NetworkResult<Pose> {
+        self.call("simGetObjectPose", (object_name,)).await
+    }
+
+    /// Moves an object, either instantly with `teleport`, or by sweeping it there.
+    ///
+    /// Returns whether the object was found and moved.
+    pub async fn sim_set_object_pose(
+        &self,
+        object_name: &str,
+        pose: Pose,
+        teleport: bool,
+    ) -> NetworkResult<bool> {
+        self.call("simSetObjectPose", (object_name, pose, teleport))
+            .await
+    }
+
+    pub async fn sim_get_object_scale(&self, object_name: &str) -> NetworkResult<Vector3r> {
+        self.call("simGetObjectScale", (object_name,)).await
+    }
+
+    /// Returns whether the object was found and scaled.
+    pub async fn sim_set_object_scale(
+        &self,
+        object_name: &str,
+        scale: Vector3r,
+    ) -> NetworkResult<bool> {
+        self.call("simSetObjectScale", (object_name, scale)).await
+    }
+
+    /// Spawns an instance of `asset_name` and returns its name.
+    ///
+    /// The simulator may pick a different name than `object_name` if it is already taken.
+    pub async fn sim_spawn_object(
+        &self,
+        object_name: &str,
+        asset_name: &str,
+        pose: Pose,
+        scale: Vector3r,
+        physics_enabled: bool,
+        is_blueprint: bool,
+    ) -> NetworkResult<String> {
+        self.call(
+            "simSpawnObject",
+            (
+                object_name,
+                asset_name,
+                pose,
+                scale,
+                physics_enabled,
+                is_blueprint,
+            ),
+        )
+        .await
+    }
+
+    /// Returns whether the object was found and destroyed.
+    pub async fn sim_destroy_object(&self, object_name: &str) -> NetworkResult<bool> {
+        self.call("simDestroyObject", (object_name,)).await
+    }
+
+    /// Sets the id `object_id` (0 to 255) on the meshes named `mesh_name`.
+    ///
+    /// With `is_name_regex`, `mesh_name` is matched as a case insensitive regex. Returns whether
+    /// a mesh was found.
+    pub async fn sim_set_segmentation_object_id(
+        &self,
+        mesh_name: &str,
+        object_id: u8,
+        is_name_regex: bool,
+    ) -> NetworkResult<bool> {
+        self.call(
+            "simSetSegmentationObjectID",
+            (mesh_name, object_id, is_name_regex),
+        )
+        .await
+    }
+
+    /// The segmentation id of a mesh, -1 if it does not exist.
+    pub async fn sim_get_segmentation_object_id(&self, mesh_name: &str) -> NetworkResult<i32> {
+        self.call("simGetSegmentationObjectID", (mesh_name,)).await
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    airsim::Client,
+    errors::NetworkResult,
+    types::{GeoPoint, Pose, Quaternionr, Vector3r},
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_repr::Deserialize_repr;
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct LidarData {
+    /// Hit points, in the vehicle frame by default, or in the sensor frame if so configured
+    #[serde(deserialize_with = "points")]
+    pub point_cloud: Vec<[f32; 3]>,
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    pub pose: Pose,
+    /// Segmentation id of the object hit by each point
+    pub segmentation: Vec<i32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ImuData {
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    pub orientation: Quaternionr,
+    pub angular_velocity: Vector3r,
+    pub linear_acceleration: Vector3r,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct BarometerData {
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    /// Meters
+    pub altitude: f32,
+    /// Pascals
+    pub pressure: f32,
+    /// Altimeter setting, in hectopascals
+    pub qnh: f32,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+pub struct MagnetometerData {
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    /// Gauss, in the body frame
+    pub magnetic_field_body: Vector3r,
+    pub magnetic_field_covariance: Vec<f32>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize_repr)]
+#[repr(u8)]
+pub enum GnssFixType {
+    #[default]
+    NoFix,
+    TimeOnly,
+    Fix2D,
+    Fix3D,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct GnssReport {
+    pub geo_point: GeoPoint,
+    /// Horizontal position error, in meters
+    pub eph: f32,
+    /// Vertical position error, in meters
+    pub epv: f32,
+    pub velocity: Vector3r,
+    pub fix_type: GnssFixType,
+    /// UTC time in microseconds
+    pub time_utc: u64,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct GpsData {
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    pub gnss: GnssReport,
+    pub is_valid: bool,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct DistanceSensorData {
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    /// Meters
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Sensor pose relative to the vehicle
+    pub relative_pose: Pose,
+}
+
+fn points<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<[f32; 3]>, D::Error> {
+    let flat = Vec::<f32>::deserialize(deserializer)?;
+    // an empty scan can come back with fewer than three values
+    Ok(flat
+        .chunks_exact(3)
+        .map(|point| [point[0], point[1], point[2]])
+        .collect())
+}
+
+impl Client {
+    pub async fn get_lidar_data(
+        &self,
+        lidar_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<LidarData> {
+        self.sensor_data("getLidarData", lidar_name, vehicle_name)
+            .await
+    }
+
+    pub async fn get_imu_data(&self, imu_name: &str, vehicle_name: &str) -> NetworkResult<ImuData> {
+        self.sensor_data("getImuData", imu_name, vehicle_name).await
+    }
+
+    pub async fn get_barometer_data(
+        &self,
+        barometer_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<BarometerData> {
+        self.sensor_data("getBarometerData", barometer_name, vehicle_name)
+            .await
+    }
+
+    pub async fn get_magnetometer_data(
+        &self,
+        magnetometer_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<MagnetometerData> {
+        self.sensor_data("getMagnetometerData", magnetometer_name, vehicle_name)
+            .await
+    }
+
+    pub async fn get_gps_data(&self, gps_name: &str, vehicle_name: &str) -> NetworkResult<GpsData> {
+        self.sensor_data("getGpsData", gps_name, vehicle_name).await
+    }
+
+    pub async fn get_distance_sensor_data(
+        &self,
+        distance_sensor_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<DistanceSensorData> {
+        self.sensor_data("getDistanceSensorData", distance_sensor_name, vehicle_name)
+            .await
+    }
+
+    /// Sensor names come from the vehicle settings, an empty name picks the first one.
+    async fn sensor_data<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        sensor_name: &str,
+        vehicle_name: &str,
+    ) -> NetworkResult<T> {
+        self.call(method, (sensor_name, vehicle_name)).await
+    }
+}
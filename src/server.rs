@@ -1,21 +1,49 @@
+use crate::msgpack::Decoder;
 use async_std::io::prelude::*;
-use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use async_std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use async_std::prelude::*;
+use async_std::task;
 use rmp_rpc::message::{Message, Notification, Request, Response};
-use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
-pub async fn listen(addrs: impl ToSocketAddrs) -> std::io::Result<()> {
+/// Shared by every connection to the same server, tests drive it with the `mock*` methods.
+struct State {
+    collision_time_stamp: AtomicU64,
+    fail_collision_info: AtomicBool,
+    /// Vehicles API control was enabled for, in order
+    api_control: Mutex<Vec<String>>,
+    /// How many of the next connections are closed right away
+    refused_connections: AtomicU32,
+}
+
+/// Serves every connection in the background, and returns the address the server is bound to.
+pub async fn listen(addrs: impl ToSocketAddrs) -> std::io::Result<SocketAddr> {
     let listener = TcpListener::bind(addrs).await?;
-    while let Some(stream) = listener.incoming().next().await {
-        println!("New connection !");
-        handle_connection(stream?).await?;
-    }
-    Ok(())
+    let local_addr = listener.local_addr()?;
+    let state = Arc::new(State {
+        collision_time_stamp: AtomicU64::new(1_602_345_678_801_234_567),
+        fail_collision_info: AtomicBool::new(false),
+        api_control: Mutex::default(),
+        refused_connections: AtomicU32::new(0),
+    });
+    task::spawn(async move {
+        while let Some(Ok(stream)) = listener.incoming().next().await {
+            let refused = state
+                .refused_connections
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if !refused {
+                task::spawn(handle_connection(stream, Arc::clone(&state)));
+            }
+        }
+    });
+    Ok(local_addr)
 }
 
 async fn handle_notification(_n: &Notification) {}
 
-fn handle_request(r: &Request) -> Response {
+fn handle_request(r: &Request, state: &State) -> Response {
     match r.method.as_ref() {
         "dostuff" => Response {
             id: r.id,
@@ -40,9 +68,27 @@ fn handle_request(r: &Request) -> Response {
             id: r.id,
             result: Ok(rmpv::Value::Integer(1.into())),
         },
-        "enableApiControl" => Response {
+        "enableApiControl" => {
+            if let [rmpv::Value::Boolean(true), rmpv::Value::String(vehicle_name)] = &r.params[..] {
+                let vehicle_name = vehicle_name.as_str().unwrap_or_default().to_string();
+                state.api_control.lock().unwrap().push(vehicle_name);
+            }
+            Response {
+                id: r.id,
+                result: Ok(rmpv::Value::Boolean(true)),
+            }
+        }
+        "mockApiControl" => Response {
             id: r.id,
-            result: Ok(rmpv::Value::Boolean(true)),
+            result: Ok(rmpv::Value::Array(
+                state
+                    .api_control
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|vehicle_name| rmpv::Value::String(vehicle_name.as_str().into()))
+                    .collect(),
+            )),
         },
         "setCarControls" => Response {
             id: r.id,
@@ -50,53 +96,247 @@ fn handle_request(r: &Request) -> Response {
         },
         "getCarState" => Response {
             id: r.id,
-            result: Ok(rmpv::Value::Integer(1.into())),
+            result: Ok(car_state()),
+        },
+        "simGetCollisionInfo" => Response {
+            id: r.id,
+            result: if state.fail_collision_info.swap(false, Ordering::SeqCst) {
+                Err(rmpv::Value::String("collision info unavailable".into()))
+            } else {
+                Ok(collision_info(
+                    state.collision_time_stamp.load(Ordering::SeqCst),
+                ))
+            },
         },
+        // a new collision, 1ms after the previous one
+        "mockCollide" => {
+            state
+                .collision_time_stamp
+                .fetch_add(1_000_000, Ordering::SeqCst);
+            Response {
+                id: r.id,
+                result: Ok(rmpv::Value::Nil),
+            }
+        }
+        // the next simGetCollisionInfo fails
+        "mockFailCollisionInfo" => {
+            state.fail_collision_info.store(true, Ordering::SeqCst);
+            Response {
+                id: r.id,
+                result: Ok(rmpv::Value::Nil),
+            }
+        }
         _ => Response {
             id: r.id,
             result: Err(rmpv::Value::String("method not implemented".into())),
         },
     }
 }
+
+pub(crate) fn map(entries: Vec<(&str, rmpv::Value)>) -> rmpv::Value {
+    rmpv::Value::Map(
+        entries
+            .into_iter()
+            .map(|(key, value)| (rmpv::Value::String(key.into()), value))
+            .collect(),
+    )
+}
+
+pub(crate) fn vector3r(x: f32, y: f32, z: f32) -> rmpv::Value {
+    map(vec![
+        ("x_val", rmpv::Value::F32(x)),
+        ("y_val", rmpv::Value::F32(y)),
+        ("z_val", rmpv::Value::F32(z)),
+    ])
+}
+
+pub(crate) fn quaternionr(w: f32, x: f32, y: f32, z: f32) -> rmpv::Value {
+    map(vec![
+        ("w_val", rmpv::Value::F32(w)),
+        ("x_val", rmpv::Value::F32(x)),
+        ("y_val", rmpv::Value::F32(y)),
+        ("z_val", rmpv::Value::F32(z)),
+    ])
+}
+
+pub(crate) fn kinematics_state() -> rmpv::Value {
+    map(vec![
+        ("position", vector3r(12.5, -3.25, -0.6)),
+        ("orientation", quaternionr(0.9659, 0., 0., 0.2588)),
+        ("linear_velocity", vector3r(7.2, 1.9, 0.)),
+        ("angular_velocity", vector3r(0., 0., 0.05)),
+        ("linear_acceleration", vector3r(0.4, 0.1, 0.)),
+        ("angular_acceleration", vector3r(0., 0., 0.)),
+    ])
+}
+
+pub(crate) fn collision_info(time_stamp: u64) -> rmpv::Value {
+    map(vec![
+        ("has_collided", rmpv::Value::Boolean(true)),
+        ("normal", vector3r(-1., 0., 0.)),
+        ("impact_point", vector3r(13.1, -3.25, -0.5)),
+        ("position", vector3r(12.5, -3.25, -0.6)),
+        ("penetration_depth", rmpv::Value::F32(0.02)),
+        ("time_stamp", rmpv::Value::Integer(time_stamp.into())),
+        ("object_name", rmpv::Value::String("Wall_12".into())),
+        ("object_id", rmpv::Value::Integer((-1).into())),
+    ])
+}
+
+fn car_state() -> rmpv::Value {
+    map(vec![
+        ("speed", rmpv::Value::F32(7.45)),
+        ("gear", rmpv::Value::Integer(2.into())),
+        ("rpm", rmpv::Value::F32(3120.5)),
+        ("maxrpm", rmpv::Value::F32(7500.)),
+        ("handbrake", rmpv::Value::Boolean(false)),
+        ("kinematics_estimated", kinematics_state()),
+        (
+            "timestamp",
+            rmpv::Value::Integer(1_602_345_678_901_234_567_u64.into()),
+        ),
+    ])
+}
+
 async fn handle_response(_r: &Response) {}
-async fn handle_message(m: &Message) -> Option<Response> {
+async fn handle_message(m: &Message, state: &State) -> Option<Response> {
     match m {
         Message::Notification(n) => {
-            handle_notification(&n).await;
+            handle_notification(n).await;
             None
         }
-        Message::Request(r) => Some(handle_request(&r)),
+        // left unanswered, like a call to a frozen simulator
+        Message::Request(r) if r.method == "mockHang" => None,
+        Message::Request(r) => Some(handle_request(r, state)),
         Message::Response(r) => {
-            handle_response(&r).await;
+            handle_response(r).await;
             None
         }
     }
 }
-async fn handle_frame(mut frame: &mut Cursor<Vec<u8>>) -> Option<Response> {
-    match Message::decode(&mut frame) {
-        Ok(message) => handle_message(&message).await,
-        _ => None,
-    }
-}
-async fn handle_connection(stream: TcpStream) -> std::io::Result<()> {
-    let mut current_message: Vec<u8> = vec![];
+async fn handle_connection(stream: TcpStream, state: Arc<State>) -> std::io::Result<()> {
     let (reader, writer) = &mut (&stream, &stream);
+    let mut decoder = Decoder::default();
     let mut buf = vec![0u8; 1024];
-    while let Ok(n) = reader.read(&mut buf).await {
-        current_message.extend(&buf[..n]);
-        let mut frame = Cursor::new(current_message.clone());
-        match handle_frame(&mut frame).await {
-            Some(response) => {
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        decoder.extend(&buf[..n]);
+        // a read can hold several messages, or only part of one
+        loop {
+            let message = match decoder.next_message() {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                // the stream can't be resynchronized after bad bytes
+                Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+            };
+            // hangs up without answering, and refuses the next connection, like a simulator
+            // reloading its level
+            if let Message::Request(r) = &message {
+                if r.method == "mockDisconnect" {
+                    state.refused_connections.store(1, Ordering::SeqCst);
+                    return Ok(());
+                }
+            }
+            if let Some(response) = handle_message(&message, &state).await {
                 writer
                     .write_all(&Message::Response(response).pack()?)
                     .await?;
-                let (_, remaining) = current_message.split_at(frame.position() as usize);
-                current_message = remaining.to_vec();
-            }
-            None => {
-                break;
             }
         }
     }
-    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{airsim::Client, errors::NetworkError};
+    use async_std::future;
+    use std::time::Duration;
+
+    #[test]
+    fn get_car_state() {
+        task::block_on(async {
+            let addr = listen("127.0.0.1:0").await.unwrap();
+            let client = Client::connect(addr).await.unwrap();
+            let state = client.get_car_state("").await.unwrap();
+            assert_eq!(state.speed, 7.45);
+            assert_eq!(state.gear, 2);
+            assert_eq!(state.maxrpm, 7500.);
+            assert!(!state.handbrake);
+            assert_eq!(state.kinematics_estimated.position.x, 12.5);
+            assert_eq!(state.kinematics_estimated.orientation.z, 0.2588);
+            assert_eq!(state.timestamp, 1_602_345_678_901_234_567);
+        });
+    }
+
+    /// The next item, or `None` if nothing comes within 200ms
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        future::timeout(Duration::from_millis(200), stream.next())
+            .await
+            .ok()
+            .flatten()
+    }
+
+    #[test]
+    fn collisions() {
+        task::block_on(async {
+            let addr = listen("127.0.0.1:0").await.unwrap();
+            let client = Client::connect(addr).await.unwrap();
+            let mut collisions = Box::pin(client.collisions("", Duration::from_millis(10)));
+            // the mock starts with a collision from before the stream existed
+            assert!(next(&mut collisions).await.is_none());
+
+            client.call::<_, ()>("mockCollide", ()).await.unwrap();
+            let collision = next(&mut collisions).await.unwrap().unwrap();
+            assert_eq!(collision.time_stamp, 1_602_345_678_802_234_567);
+            // yielded once, though every poll keeps reporting it
+            assert!(next(&mut collisions).await.is_none());
+
+            client
+                .call::<_, ()>("mockFailCollisionInfo", ())
+                .await
+                .unwrap();
+            assert!(matches!(
+                next(&mut collisions).await,
+                Some(Err(NetworkError::Rpc(_)))
+            ));
+            // polling goes on after the error
+            client.call::<_, ()>("mockCollide", ()).await.unwrap();
+            let collision = next(&mut collisions).await.unwrap().unwrap();
+            assert_eq!(collision.time_stamp, 1_602_345_678_803_234_567);
+        });
+    }
+
+    #[test]
+    fn timeouts() {
+        task::block_on(async {
+            let addr = listen("127.0.0.1:0").await.unwrap();
+            let mut client = Client::connect(addr).await.unwrap();
+            let hang = client
+                .call_with_timeout::<_, ()>("mockHang", (), Duration::from_millis(50))
+                .await;
+            assert!(matches!(hang, Err(NetworkError::Timeout)));
+
+            client.set_request_timeout(Some(Duration::from_millis(50)));
+            let hang = client.call::<_, ()>("mockHang", ()).await;
+            assert!(matches!(hang, Err(NetworkError::Timeout)));
+
+            // the connection still works
+            client.get_car_state("").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn bad_bytes_close_the_connection() {
+        task::block_on(async {
+            let addr = listen("127.0.0.1:0").await.unwrap();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&[0xc1]).await.unwrap();
+            let mut buf = [0_u8; 16];
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+        });
+    }
 }
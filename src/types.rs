@@ -0,0 +1,340 @@
+//! Geometric and physical types shared by the simulator APIs.
+//!
+//! Everything is expressed in the simulator's NED frame: x north, y east, z down, in meters.
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Vector2r {
+    #[serde(rename = "x_val")]
+    pub x: f32,
+    #[serde(rename = "y_val")]
+    pub y: f32,
+}
+
+impl Vector2r {
+    #[must_use]
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Vector3r {
+    #[serde(rename = "x_val")]
+    pub x: f32,
+    #[serde(rename = "y_val")]
+    pub y: f32,
+    #[serde(rename = "z_val")]
+    pub z: f32,
+}
+
+impl Vector3r {
+    #[must_use]
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    #[must_use]
+    pub fn dot(&self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    #[must_use]
+    pub fn cross(&self, other: Self) -> Self {
+        Self {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.dot(*self).sqrt()
+    }
+
+    #[must_use]
+    pub fn distance_to(&self, other: Self) -> f32 {
+        (other - *self).length()
+    }
+}
+
+impl Add for Vector3r {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl AddAssign for Vector3r {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vector3r {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl SubAssign for Vector3r {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Vector3r {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<f32> for Vector3r {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        Self::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternionr {
+    #[serde(rename = "w_val")]
+    pub w: f32,
+    #[serde(rename = "x_val")]
+    pub x: f32,
+    #[serde(rename = "y_val")]
+    pub y: f32,
+    #[serde(rename = "z_val")]
+    pub z: f32,
+}
+
+impl Quaternionr {
+    #[must_use]
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// Builds the rotation from Euler angles in radians, applied yaw first, then pitch, then roll.
+    #[must_use]
+    pub fn from_euler_angles(roll: f32, pitch: f32, yaw: f32) -> Self {
+        let (sin_roll, cos_roll) = (roll * 0.5).sin_cos();
+        let (sin_pitch, cos_pitch) = (pitch * 0.5).sin_cos();
+        let (sin_yaw, cos_yaw) = (yaw * 0.5).sin_cos();
+        Self {
+            w: cos_yaw * cos_roll * cos_pitch + sin_yaw * sin_roll * sin_pitch,
+            x: cos_yaw * sin_roll * cos_pitch - sin_yaw * cos_roll * sin_pitch,
+            y: cos_yaw * cos_roll * sin_pitch + sin_yaw * sin_roll * cos_pitch,
+            z: sin_yaw * cos_roll * cos_pitch - cos_yaw * sin_roll * sin_pitch,
+        }
+    }
+
+    /// Returns `(roll, pitch, yaw)` in radians, the inverse of `from_euler_angles`.
+    #[must_use]
+    pub fn to_euler_angles(&self) -> (f32, f32, f32) {
+        let roll = (2. * (self.w * self.x + self.y * self.z))
+            .atan2(1. - 2. * (self.x * self.x + self.y * self.y));
+        // clamped since rounding errors can push it out of asin's domain near +-90 degrees
+        let pitch = (2. * (self.w * self.y - self.z * self.x))
+            .clamp(-1., 1.)
+            .asin();
+        let yaw = (2. * (self.w * self.z + self.x * self.y))
+            .atan2(1. - 2. * (self.y * self.y + self.z * self.z));
+        (roll, pitch, yaw)
+    }
+
+    #[must_use]
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        let length = self.length();
+        Self::new(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    /// Rotates `v` by this quaternion, which is expected to be normalized.
+    #[must_use]
+    pub fn rotate(&self, v: Vector3r) -> Vector3r {
+        let axis = Vector3r::new(self.x, self.y, self.z);
+        let t = axis.cross(v) * 2.;
+        v + t * self.w + axis.cross(t)
+    }
+}
+
+impl Default for Quaternionr {
+    fn default() -> Self {
+        Self::new(1., 0., 0., 0.)
+    }
+}
+
+/// Hamilton product: `a * b` rotates by `b` first, then by `a`.
+impl Mul for Quaternionr {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pose {
+    pub position: Vector3r,
+    pub orientation: Quaternionr,
+}
+
+impl Pose {
+    #[must_use]
+    pub fn new(position: Vector3r, orientation: Quaternionr) -> Self {
+        Self {
+            position,
+            orientation,
+        }
+    }
+
+    /// Expresses `point`, given in this pose's frame, in the parent frame.
+    #[must_use]
+    pub fn transform(&self, point: Vector3r) -> Vector3r {
+        self.position + self.orientation.rotate(point)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KinematicsState {
+    pub position: Vector3r,
+    pub orientation: Quaternionr,
+    pub linear_velocity: Vector3r,
+    pub angular_velocity: Vector3r,
+    pub linear_acceleration: Vector3r,
+    pub angular_acceleration: Vector3r,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: f32,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentState {
+    pub position: Vector3r,
+    pub geo_point: GeoPoint,
+    /// Meters per second squared
+    pub gravity: Vector3r,
+    /// Pascals
+    pub air_pressure: f32,
+    /// Kelvins
+    pub temperature: f32,
+    /// Kilograms per cubic meter
+    pub air_density: f32,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollisionInfo {
+    pub has_collided: bool,
+    pub normal: Vector3r,
+    pub impact_point: Vector3r,
+    pub position: Vector3r,
+    pub penetration_depth: f32,
+    /// Simulation time in nanoseconds
+    pub time_stamp: u64,
+    pub object_name: String,
+    pub object_id: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANGLES: [f32; 5] = [-2.5, -1., 0., 0.7, 3.];
+
+    fn assert_close(a: Vector3r, b: Vector3r) {
+        assert!((a - b).length() < 1e-5, "{:?} != {:?}", a, b);
+    }
+
+    /// Rz(yaw) * Ry(pitch) * Rx(roll) * v, computed without quaternions
+    fn rotate_with_matrix(roll: f32, pitch: f32, yaw: f32, v: Vector3r) -> Vector3r {
+        let (sr, cr) = roll.sin_cos();
+        let (sp, cp) = pitch.sin_cos();
+        let (sy, cy) = yaw.sin_cos();
+        Vector3r::new(
+            cy * cp * v.x + (cy * sp * sr - sy * cr) * v.y + (cy * sp * cr + sy * sr) * v.z,
+            sy * cp * v.x + (sy * sp * sr + cy * cr) * v.y + (sy * sp * cr - cy * sr) * v.z,
+            -sp * v.x + cp * sr * v.y + cp * cr * v.z,
+        )
+    }
+
+    #[test]
+    fn euler_angles_round_trip() {
+        for &roll in &ANGLES {
+            // pitch stays within +-90 degrees, past that another triplet is the same rotation
+            for &pitch in &[-1.5, -0.4, 0., 0.9, 1.5] {
+                for &yaw in &ANGLES {
+                    let q = Quaternionr::from_euler_angles(roll, pitch, yaw);
+                    assert!((q.length() - 1.).abs() < 1e-5);
+                    let (r, p, y) = q.to_euler_angles();
+                    let back = Quaternionr::from_euler_angles(r, p, y);
+                    // q and -q are the same rotation
+                    let v = Vector3r::new(1., 2., 3.);
+                    assert_close(back.rotate(v), q.rotate(v));
+                    assert!((p - pitch).abs() < 1e-3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_matches_rotation_matrix() {
+        let v = Vector3r::new(0.3, -1.2, 2.);
+        for &roll in &ANGLES {
+            for &pitch in &ANGLES {
+                for &yaw in &ANGLES {
+                    let q = Quaternionr::from_euler_angles(roll, pitch, yaw);
+                    assert_close(q.rotate(v), rotate_with_matrix(roll, pitch, yaw, v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hamilton_product() {
+        let i = Quaternionr::new(0., 1., 0., 0.);
+        let j = Quaternionr::new(0., 0., 1., 0.);
+        let k = Quaternionr::new(0., 0., 0., 1.);
+        assert_eq!(i * j, k);
+        assert_eq!(j * k, i);
+        assert_eq!(k * i, j);
+        assert_eq!(j * i, Quaternionr::new(0., 0., 0., -1.));
+        assert_eq!(i * i, Quaternionr::new(-1., 0., 0., 0.));
+
+        let a = Quaternionr::from_euler_angles(0.2, -0.5, 1.1);
+        let b = Quaternionr::from_euler_angles(-1.3, 0.4, 2.);
+        let v = Vector3r::new(1., -2., 0.5);
+        assert_close((a * b).rotate(v), a.rotate(b.rotate(v)));
+        assert_close((a * a.conjugate()).rotate(v), v);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{airsim::Client, errors::NetworkResult, types::Vector3r};
+use serde_repr::Serialize_repr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize_repr)]
+#[repr(u8)]
+pub enum WeatherParameter {
+    Rain,
+    RoadWetness,
+    Snow,
+    RoadSnow,
+    MapleLeaf,
+    RoadLeaf,
+    Dust,
+    Fog,
+}
+
+/// A pending time of day change, sent to the simulator by `send`.
+pub struct TimeOfDayCommand<'a> {
+    client: &'a Client,
+    is_enabled: bool,
+    start_datetime: String,
+    is_start_datetime_dst: bool,
+    celestial_clock_speed: f32,
+    update_interval_secs: f32,
+    move_sun: bool,
+}
+
+impl<'a> TimeOfDayCommand<'a> {
+    /// `"%Y-%m-%d %H:%M:%S"`, an empty string keeps the current time
+    #[must_use]
+    pub fn start_datetime(mut self, start_datetime: &str) -> Self {
+        self.start_datetime = start_datetime.to_string();
+        self
+    }
+
+    #[must_use]
+    pub fn is_start_datetime_dst(mut self, is_start_datetime_dst: bool) -> Self {
+        self.is_start_datetime_dst = is_start_datetime_dst;
+        self
+    }
+
+    /// How much faster than the simulation clock the sun moves
+    #[must_use]
+    pub fn celestial_clock_speed(mut self, celestial_clock_speed: f32) -> Self {
+        self.celestial_clock_speed = celestial_clock_speed;
+        self
+    }
+
+    /// How often the sun position is updated, in seconds
+    #[must_use]
+    pub fn update_interval_secs(mut self, update_interval_secs: f32) -> Self {
+        self.update_interval_secs = update_interval_secs;
+        self
+    }
+
+    #[must_use]
+    pub fn move_sun(mut self, move_sun: bool) -> Self {
+        self.move_sun = move_sun;
+        self
+    }
+
+    pub async fn send(self) -> NetworkResult<()> {
+        self.client
+            .call(
+                "simSetTimeOfDay",
+                (
+                    self.is_enabled,
+                    self.start_datetime.as_str(),
+                    self.is_start_datetime_dst,
+                    self.celestial_clock_speed,
+                    self.update_interval_secs,
+                    self.move_sun,
+                ),
+            )
+            .await
+    }
+}
+
+impl Client {
+    /// Weather effects are disabled until this is called.
+    pub async fn sim_enable_weather(&self, enable: bool) -> NetworkResult<()> {
+        self.call("simEnableWeather", (enable,)).await
+    }
+
+    /// `value` ranges from 0 (none) to 1 (heaviest).
+    pub async fn sim_set_weather_parameter(
+        &self,
+        parameter: WeatherParameter,
+        value: f32,
+    ) -> NetworkResult<()> {
+        self.call("simSetWeatherParameter", (parameter, value))
+            .await
+    }
+
+    /// Enables or disables the day cycle, the other settings default to the simulator's.
+    #[must_use]
+    pub fn sim_set_time_of_day(&self, is_enabled: bool) -> TimeOfDayCommand<'_> {
+        TimeOfDayCommand {
+            client: self,
+            is_enabled,
+            start_datetime: String::new(),
+            is_start_datetime_dst: false,
+            celestial_clock_speed: 1.,
+            update_interval_secs: 60.,
+            move_sun: true,
+        }
+    }
+
+    /// Wind velocity in meters per second, in the world NED frame.
+    pub async fn sim_set_wind(&self, wind: Vector3r) -> NetworkResult<()> {
+        self.call("simSetWind", (wind,)).await
+    }
+}